@@ -10,23 +10,229 @@ This directory usually contains your project's root Cargo.toml. The path to our
 We're going to fix that by modifying our build script to copy our res folder to where Cargo
  creates our executable, and we'll reference it from there.
 
+On top of the copy the build script is our asset pipeline:
+ - every obj and mtl is loaded with the engine's own parser, and every image in res/ or
+   named by a material is decoded. a broken asset, or a material no mtl defines, fails the
+   build and says which file and line is wrong
+ - obj files are converted to binary .mesh caches next to the copy in OUT_DIR
+ - OUT_DIR/asset_manifest.rs gets a typed constant for every asset, see game_engine::assets.
+   the .mesh files are embedded in the binary, so it does not depend on OUT_DIR at runtime.
+   paths are kept relative (res/cube.obj), the game resolves them with assets::asset_root
 */
 
 use anyhow::*;
 use fs_extra::copy_items;
 use fs_extra::dir::CopyOptions;
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
+
+// the engine's own mesh crate, so assets are checked and converted exactly the
+// way they are loaded at runtime. cargo reruns this script when it changes
+use engine_mesh::{material::Material, mesh_cache::CachedMesh, obj_parser};
+
+/// every file under a folder, sorted so the manifest is the same on every build
+fn files_in(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(folder).with_context(|| folder.display().to_string())? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(files_in(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// res/props/Old Crate.obj -> PROPS_OLD_CRATE
+fn constant_name(path: &Path) -> String {
+    let name: String = path
+        .with_extension("")
+        .strip_prefix("res")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('_').to_string();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// the problem with an image, if it can not be read or decoded
+fn check_image(path: &Path) -> Option<String> {
+    image::open(path)
+        .err()
+        .map(|e| format!("{}: {}", path.display(), e))
+}
+
+fn add_constant(
+    constants: &mut BTreeMap<String, String>,
+    path: &Path,
+    definition: String,
+) -> Result<()> {
+    let name = constant_name(path);
+    if constants.insert(name.clone(), definition).is_some() {
+        bail!(
+            "{}: another asset of the same kind is also called {}, rename one of them",
+            path.display(),
+            name
+        );
+    }
+    Ok(())
+}
+
+fn write_module(
+    manifest: &mut String,
+    module: &str,
+    asset_type: &str,
+    constants: &BTreeMap<String, String>,
+) {
+    manifest.push_str(&format!(
+        "pub mod {} {{\n    use super::{};\n\n",
+        module, asset_type
+    ));
+    for (name, definition) in constants {
+        manifest.push_str(&format!(
+            "    pub const {}: {} = {};\n",
+            name, asset_type, definition
+        ));
+    }
+    let names: Vec<&str> = constants.keys().map(|name| name.as_str()).collect();
+    manifest.push_str(&format!(
+        "\n    pub const ALL: &[{}] = &[{}];\n}}\n\n",
+        asset_type,
+        names.join(", ")
+    ));
+}
 
 fn main() -> Result<()> {
-    // This tells Cargo to rerun this script if something in /res/ changes.
-    println!("cargo:rerun-if-changed=res/*");
+    // a folder makes Cargo look at everything inside it, a glob like res/* is not understood
+    println!("cargo:rerun-if-changed=res");
+    println!("cargo:rerun-if-changed=build.rs");
 
     let out_dir = env::var("OUT_DIR")?;
     let mut copy_options = CopyOptions::new();
     copy_options.overwrite = true;
-    let mut paths_to_copy = Vec::new();
-    paths_to_copy.push("res/");
-    copy_items(&paths_to_copy, out_dir, &copy_options)?;
+    let paths_to_copy = vec!["res/"];
+    copy_items(&paths_to_copy, &out_dir, &copy_options)?;
+    // and next to the executable (OUT_DIR is target/<profile>/build/<crate>/out),
+    // where release builds look for it, see assets::asset_root
+    if let Some(exe_dir) = Path::new(&out_dir).ancestors().nth(3) {
+        copy_items(&paths_to_copy, exe_dir, &copy_options)?;
+    }
+
+    let mut broken = Vec::new();
+    let mut models = BTreeMap::new();
+    let mut textures = BTreeMap::new();
+    let mut next_id = 0u32;
+
+    for path in files_in(Path::new("res"))? {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let source = path.to_string_lossy().replace('\\', "/");
+
+        match extension.as_str() {
+            "obj" => {
+                // the engine only warns about missing libraries or materials, the build is stricter
+                let meshes = match obj_parser::load_obj_meshes(&source) {
+                    Result::Ok(meshes) => meshes,
+                    Err(e) => {
+                        broken.push(e.to_string());
+                        continue;
+                    }
+                };
+                let mut broken_materials = false;
+                for mesh in &meshes {
+                    if let (Some(name), None) = (&mesh.material_name, &mesh.material) {
+                        broken.push(format!(
+                            "{}: material '{}' is not defined by any of its mtl files",
+                            source, name
+                        ));
+                        broken_materials = true;
+                    }
+                }
+                // the textures the materials name may live outside res/
+                for material in meshes.iter().filter_map(|mesh| mesh.material.as_ref()) {
+                    for texture in [&material.diffuse_map, &material.bump_map]
+                        .into_iter()
+                        .flatten()
+                    {
+                        if let Some(e) = check_image(texture) {
+                            broken.push(format!(
+                                "{}: material '{}' uses {}",
+                                source, material.name, e
+                            ));
+                            broken_materials = true;
+                        }
+                    }
+                }
+                if broken_materials {
+                    continue;
+                }
+
+                let mesh_path = path.with_extension("mesh");
+                match CachedMesh::convert_obj(&source) {
+                    Result::Ok(mesh) => mesh.write(&Path::new(&out_dir).join(&mesh_path))?,
+                    Err(e) => {
+                        broken.push(e.to_string());
+                        continue;
+                    }
+                }
+                add_constant(
+                    &mut models,
+                    &path,
+                    format!(
+                        "ModelAsset {{ id: {}, path: {:?}, mesh: include_bytes!(concat!(env!(\"OUT_DIR\"), {:?})) }}",
+                        next_id,
+                        source,
+                        format!("/{}", mesh_path.to_string_lossy().replace('\\', "/"))
+                    ),
+                )?;
+                next_id += 1;
+            }
+            "mtl" => {
+                if let Err(e) = Material::load_mtl_file(&source) {
+                    broken.push(e.to_string());
+                }
+            }
+            "png" | "jpg" | "jpeg" => {
+                if let Some(e) = check_image(&path) {
+                    broken.push(e);
+                    continue;
+                }
+                add_constant(
+                    &mut textures,
+                    &path,
+                    format!("TextureAsset {{ id: {}, path: {:?} }}", next_id, source),
+                )?;
+                next_id += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if !broken.is_empty() {
+        bail!("broken assets in res/:\n  {}", broken.join("\n  "));
+    }
+
+    let mut manifest = String::from("// generated by build.rs from the files in res/\n\n");
+    write_module(&mut manifest, "models", "ModelAsset", &models);
+    write_module(&mut manifest, "textures", "TextureAsset", &textures);
+    std::fs::write(Path::new(&out_dir).join("asset_manifest.rs"), manifest)?;
 
     Ok(())
 }
@@ -0,0 +1,112 @@
+///
+/// bounds - axis aligned boxes and spheres around meshes and entities
+///
+use glam::{Mat4, Vec3};
+
+/// axis aligned bounding box
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// smallest box around the points, a zero sized box at the origin if there are none
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        points
+            .into_iter()
+            .fold(None, |bounds: Option<Aabb>, p| match bounds {
+                Some(aabb) => Some(Aabb {
+                    min: aabb.min.min(p),
+                    max: aabb.max.max(p),
+                }),
+                None => Some(Aabb { min: p, max: p }),
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// half the size along each axis
+    pub fn extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    /// smallest box that holds both
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    ///
+    /// the box around this one once moved by `matrix`. a rotated box no
+    /// longer lines up with the axes, so the result is the box around it
+    ///
+    pub fn transform(&self, matrix: &Mat4) -> Aabb {
+        let center = matrix.transform_point3(self.center());
+        let extents = self.extents();
+        let extents = matrix.x_axis.truncate().abs() * extents.x
+            + matrix.y_axis.truncate().abs() * extents.y
+            + matrix.z_axis.truncate().abs() * extents.z;
+        Aabb {
+            min: center - extents,
+            max: center + extents,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    ///
+    /// a sphere around the points centred on their bounding box. not the
+    /// smallest one possible, but close for most meshes and cheap to find
+    ///
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> BoundingSphere {
+        let center = Aabb::from_points(points.clone()).center();
+        let radius = points
+            .into_iter()
+            .map(|p| p.distance(center))
+            .fold(0.0, f32::max);
+        BoundingSphere { center, radius }
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.distance_squared(self.center) <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        let reach = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= reach * reach
+    }
+
+    /// the sphere once moved by `matrix`, grown by its largest axis scale
+    pub fn transform(&self, matrix: &Mat4) -> BoundingSphere {
+        let scale = matrix
+            .x_axis
+            .truncate()
+            .length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+        BoundingSphere {
+            center: matrix.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//!
+//! engine_mesh - the cpu side of loading meshes
+//!
+//! obj and mtl parsing, normal / tangent generation, levels of detail and the
+//! binary .mesh cache. nothing here touches wgpu, so the build script can
+//! check and convert assets with exactly the code the engine loads them with.
+//! the engine re-exports every module under game_engine
+//!
+pub mod bounds;
+pub mod material;
+pub mod mesh_cache;
+pub mod mesh_processing;
+pub mod mesh_simplification;
+pub mod obj_error;
+pub mod obj_parser;
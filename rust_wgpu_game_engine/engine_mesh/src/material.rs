@@ -0,0 +1,163 @@
+///
+/// Material - surface properties read from a wavefront .mtl library
+///
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use glam::Vec3;
+
+use crate::obj_error::{ObjError, ObjErrorKind};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub diffuse_colour: Vec3,         // Kd
+    pub specular_colour: Vec3,        // Ks
+    pub shininess: f32,               // Ns
+    pub dissolve: f32,                // d - 1.0 is fully opaque
+    pub diffuse_map: Option<PathBuf>, // map_Kd, relative to the .mtl file
+    pub bump_map: Option<PathBuf>,    // map_Bump / bump, relative to the .mtl file
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse_colour: Vec3::ONE,
+            specular_colour: Vec3::ZERO,
+            shininess: 1.0,
+            dissolve: 1.0,
+            diffuse_map: None,
+            bump_map: None,
+        }
+    }
+
+    /// shine damper as used by ModelTexture, never below 1
+    pub fn get_shine_damper(&self) -> f32 {
+        self.shininess.max(1.0)
+    }
+
+    /// reflectivity as used by ModelTexture - the strongest specular channel
+    pub fn get_reflectivity(&self) -> f32 {
+        self.specular_colour.max_element()
+    }
+
+    pub fn has_transparency(&self) -> bool {
+        self.dissolve < 1.0
+    }
+
+    /// r g b, or a single value for a grey
+    fn parse_colour(
+        current_line: &[&str],
+        filename: &str,
+        line_number: usize,
+    ) -> Result<Vec3, ObjError> {
+        if current_line.len() == 2 {
+            let grey = Material::parse_float(current_line, 1, filename, line_number)?;
+            return Ok(Vec3::splat(grey));
+        }
+        let mut colour = [0.0; 3];
+        for (i, channel) in colour.iter_mut().enumerate() {
+            *channel = Material::parse_float(current_line, i + 1, filename, line_number)?;
+        }
+        Ok(Vec3::from_array(colour))
+    }
+
+    fn parse_float(
+        current_line: &[&str],
+        index: usize,
+        filename: &str,
+        line_number: usize,
+    ) -> Result<f32, ObjError> {
+        let value = current_line.get(index).copied().unwrap_or("");
+        value.parse::<f32>().map_err(|_| {
+            ObjError::new(
+                filename,
+                line_number,
+                ObjErrorKind::MalformedFloat(value.to_string()),
+            )
+        })
+    }
+
+    fn map_path(current_line: &[&str], directory: &Path) -> Option<PathBuf> {
+        match current_line {
+            [_, .., file] => Some(directory.join(file)),
+            _ => None,
+        }
+    }
+
+    ///
+    /// reads every material in a .mtl file, keyed by name.
+    /// texture map options (-s, -o, -bm ...) are skipped, the file name is taken
+    /// as the last word on the line
+    ///
+    pub fn load_mtl_file(filename: &str) -> Result<HashMap<String, Material>, ObjError> {
+        let file = File::open(filename)
+            .map_err(|e| ObjError::new(filename, 0, ObjErrorKind::MissingFile(e)))?;
+        let reader = BufReader::new(file);
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+
+        let mut materials = HashMap::new();
+        let mut current: Option<Material> = None;
+
+        for (line_index, line_from_file) in reader.lines().enumerate() {
+            let line_number = line_index + 1;
+            let line = line_from_file
+                .map_err(|e| ObjError::new(filename, line_number, ObjErrorKind::Io(e)))?;
+            let current_line: Vec<&str> = line.split_whitespace().collect();
+            let Some(&keyword) = current_line.first() else {
+                continue;
+            };
+
+            if keyword == "newmtl" {
+                if let Some(material) = current.take() {
+                    materials.insert(material.name.clone(), material);
+                }
+                current = Some(Material::new(current_line[1..].join(" ").as_str()));
+                continue;
+            }
+
+            // anything before the first newmtl has nothing to apply to
+            let Some(material) = current.as_mut() else {
+                continue;
+            };
+            match keyword {
+                "Kd" => {
+                    material.diffuse_colour =
+                        Material::parse_colour(&current_line, filename, line_number)?
+                }
+                "Ks" => {
+                    material.specular_colour =
+                        Material::parse_colour(&current_line, filename, line_number)?
+                }
+                "Ns" => {
+                    material.shininess =
+                        Material::parse_float(&current_line, 1, filename, line_number)?
+                }
+                "d" => {
+                    material.dissolve =
+                        Material::parse_float(&current_line, 1, filename, line_number)?
+                }
+                // Tr is the inverse of d, some exporters write it instead
+                "Tr" => {
+                    material.dissolve =
+                        1.0 - Material::parse_float(&current_line, 1, filename, line_number)?
+                }
+                "map_Kd" => material.diffuse_map = Material::map_path(&current_line, directory),
+                "map_Bump" | "map_bump" | "bump" => {
+                    material.bump_map = Material::map_path(&current_line, directory)
+                }
+                _ => {}
+            }
+        }
+        if let Some(material) = current.take() {
+            materials.insert(material.name.clone(), material);
+        }
+
+        Ok(materials)
+    }
+}
@@ -0,0 +1,431 @@
+///
+/// CachedMesh - preprocessed binary meshes, so big obj files are only parsed once
+///
+/// a .mesh file is a MeshHeader followed by the raw vertex and index arrays,
+/// exactly as they go into the gpu buffers:
+///
+///   header      magic "RMSH", version, hash of the source file, the vertex
+///               layout it was written with, bounding box and sphere, counts
+///   vertices    vertex_count * vertex_stride bytes of Vertex
+///   indices     index_count u16 or u32, whichever the mesh needs, every
+///               level of detail one after another
+///   lods        lod_count pairs of u32, first index and index count
+///
+/// numbers are stored in the byte order of the machine that wrote the file.
+/// a cache is thrown away and rebuilt when its version, vertex layout or
+/// source hash no longer match. caches live in their own folder (see
+/// cache_dir), not next to the source, so writing one does not touch res/
+/// where the build script and hot reload would see it.
+///
+/// nothing here touches the gpu, the build script converts meshes with it too
+///
+use std::{
+    fmt,
+    mem::offset_of,
+    path::{Path, PathBuf},
+};
+
+use glam::Vec3;
+
+use crate::{
+    bounds::{Aabb, BoundingSphere},
+    mesh_simplification::{Lod, LodSettings},
+    obj_error::ObjError,
+    obj_parser::{self, ObjData, Vertex, vertex_bounds},
+};
+
+const MAGIC: [u8; 4] = *b"RMSH";
+/// bump whenever the layout of the file, or what is written into it, changes
+/// 3 - levels of detail after the indices
+/// 4 - bounding sphere in the header
+/// 5 - attribute sizes in the vertex layout instead of wgpu formats
+/// 6 - obj files without normals get smooth generated ones instead of flat
+pub const MESH_CACHE_VERSION: u32 = 6;
+const MAX_ATTRIBUTES: usize = 8;
+
+#[derive(Debug)]
+pub enum MeshCacheError {
+    Io(PathBuf, std::io::Error),
+    Obj(ObjError),
+    /// the file does not start with the mesh magic
+    NotAMeshCache,
+    /// written by another version of the engine
+    Version {
+        found: u32,
+        expected: u32,
+    },
+    /// written with a different Vertex layout
+    LayoutMismatch,
+    /// the file is shorter than its header says
+    Truncated,
+    /// an index points past the vertices, the file is corrupt
+    IndexOutOfRange {
+        index: u32,
+        vertex_count: u32,
+    },
+}
+
+impl fmt::Display for MeshCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshCacheError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            MeshCacheError::Obj(e) => write!(f, "{}", e),
+            MeshCacheError::NotAMeshCache => write!(f, "not a mesh cache file"),
+            MeshCacheError::Version { found, expected } => write!(
+                f,
+                "mesh cache version {} is not supported, expected {}",
+                found, expected
+            ),
+            MeshCacheError::LayoutMismatch => {
+                write!(f, "mesh cache was written with a different vertex layout")
+            }
+            MeshCacheError::Truncated => write!(f, "mesh cache file is truncated"),
+            MeshCacheError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "mesh cache index {} is past its {} vertices",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshCacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MeshCacheError::Io(_, e) => Some(e),
+            MeshCacheError::Obj(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ObjError> for MeshCacheError {
+    fn from(e: ObjError) -> Self {
+        MeshCacheError::Obj(e)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeshHeader {
+    magic: [u8; 4],
+    version: u32,
+    source_hash: u64,
+    vertex_stride: u32,
+    attribute_count: u32,
+    /// shader location, size in bytes, offset of each attribute
+    attributes: [[u32; 3]; MAX_ATTRIBUTES],
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    /// centre and radius
+    bounding_sphere: [f32; 4],
+    /// 2 or 4
+    index_size: u32,
+    vertex_count: u32,
+    /// across every level of detail
+    index_count: u32,
+    lod_count: u32,
+}
+
+impl MeshHeader {
+    /// the vertex layout part of the header for the current Vertex,
+    /// in the shader locations of Vertex::desc
+    fn vertex_layout() -> (u32, u32, [[u32; 3]; MAX_ATTRIBUTES]) {
+        let vertex_attributes = [
+            [0, size_of::<[f32; 3]>(), offset_of!(Vertex, position)],
+            [1, size_of::<[f32; 2]>(), offset_of!(Vertex, tex_coords)],
+            [2, size_of::<[f32; 3]>(), offset_of!(Vertex, normal)],
+        ];
+        let mut attributes = [[0; 3]; MAX_ATTRIBUTES];
+        for (slot, attribute) in attributes.iter_mut().zip(vertex_attributes) {
+            *slot = attribute.map(|value| value as u32);
+        }
+        (
+            size_of::<Vertex>() as u32,
+            vertex_attributes.len() as u32,
+            attributes,
+        )
+    }
+}
+
+/// a mesh as stored in a .mesh file, ready to upload
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedMesh {
+    pub source_hash: u64,
+    pub aabb: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    /// bytes per index, 2 or 4
+    pub index_size: u32,
+    pub vertex_count: u32,
+    pub index_count: u32,
+    pub vertex_bytes: Vec<u8>,
+    pub index_bytes: Vec<u8>,
+    /// ranges of index_bytes, full detail first
+    pub lods: Vec<Lod>,
+}
+
+impl CachedMesh {
+    /// bytes per index for `vertex_count` vertices, the same as RawModel::index_format_for
+    pub fn index_size_for(vertex_count: usize) -> u32 {
+        if vertex_count <= u16::MAX as usize {
+            2
+        } else {
+            4
+        }
+    }
+
+    /// 64 bit FNV-1a, stable between runs and rust versions unlike DefaultHasher
+    pub fn hash_source(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// the folder runtime caches are written to - MESH_CACHE_DIR if it is set,
+    /// otherwise one in the temp folder
+    pub fn cache_dir() -> PathBuf {
+        match std::env::var_os("MESH_CACHE_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::temp_dir().join("engine_mesh_cache"),
+        }
+    }
+
+    ///
+    /// where the cache of a source file lives - in cache_dir, named after the
+    /// file and a hash of its full path so files with the same name in
+    /// different folders do not share a cache
+    ///
+    pub fn cache_path_for(source: &str) -> PathBuf {
+        let full_path = std::fs::canonicalize(source).unwrap_or_else(|_| PathBuf::from(source));
+        let path_hash = CachedMesh::hash_source(full_path.to_string_lossy().as_bytes());
+        let stem = full_path
+            .file_stem()
+            .map_or("mesh".into(), |stem| stem.to_string_lossy());
+        CachedMesh::cache_dir().join(format!("{}-{:016x}.mesh", stem, path_hash))
+    }
+
+    /// converts a mesh, simplifying it into levels of detail with the default LodSettings
+    pub fn from_obj_data(data: &ObjData, source_hash: u64) -> CachedMesh {
+        let vertices = data.to_vertices();
+        let lods = data.generate_lods(&LodSettings::default());
+        let indices = lods.concat();
+        let (aabb, bounding_sphere) = vertex_bounds(&vertices);
+
+        let index_size = CachedMesh::index_size_for(vertices.len());
+        let index_bytes = if index_size == 2 {
+            let indices: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+            bytemuck::cast_slice(&indices).to_vec()
+        } else {
+            bytemuck::cast_slice(&indices).to_vec()
+        };
+
+        CachedMesh {
+            source_hash,
+            aabb,
+            bounding_sphere,
+            index_size,
+            vertex_count: vertices.len() as u32,
+            index_count: indices.len() as u32,
+            vertex_bytes: bytemuck::cast_slice(&vertices).to_vec(),
+            index_bytes,
+            lods: Lod::ranges(&lods),
+        }
+    }
+
+    /// parses an obj file into a cached mesh, without writing anything
+    pub fn convert_obj(source: &str) -> Result<CachedMesh, MeshCacheError> {
+        let bytes =
+            std::fs::read(source).map_err(|e| MeshCacheError::Io(PathBuf::from(source), e))?;
+        let data = obj_parser::load_obj_file(source)?;
+        Ok(CachedMesh::from_obj_data(
+            &data,
+            CachedMesh::hash_source(&bytes),
+        ))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (vertex_stride, attribute_count, attributes) = MeshHeader::vertex_layout();
+        let header = MeshHeader {
+            magic: MAGIC,
+            version: MESH_CACHE_VERSION,
+            source_hash: self.source_hash,
+            vertex_stride,
+            attribute_count,
+            attributes,
+            bounds_min: self.aabb.min.to_array(),
+            bounds_max: self.aabb.max.to_array(),
+            bounding_sphere: self
+                .bounding_sphere
+                .center
+                .extend(self.bounding_sphere.radius)
+                .to_array(),
+            index_size: self.index_size,
+            vertex_count: self.vertex_count,
+            index_count: self.index_count,
+            lod_count: self.lods.len() as u32,
+        };
+        let lods: Vec<[u32; 2]> = self
+            .lods
+            .iter()
+            .map(|lod| [lod.first_index, lod.index_count])
+            .collect();
+
+        let mut bytes = Vec::with_capacity(
+            std::mem::size_of::<MeshHeader>()
+                + self.vertex_bytes.len()
+                + self.index_bytes.len()
+                + lods.len() * 8,
+        );
+        bytes.extend_from_slice(bytemuck::bytes_of(&header));
+        bytes.extend_from_slice(&self.vertex_bytes);
+        bytes.extend_from_slice(&self.index_bytes);
+        bytes.extend_from_slice(bytemuck::cast_slice(&lods));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<CachedMesh, MeshCacheError> {
+        let header_size = std::mem::size_of::<MeshHeader>();
+        if bytes.len() < 8 || bytes[..4] != MAGIC {
+            return Err(MeshCacheError::NotAMeshCache);
+        }
+        // check the version before the rest, older headers may be a different size
+        let version = u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if version != MESH_CACHE_VERSION {
+            return Err(MeshCacheError::Version {
+                found: version,
+                expected: MESH_CACHE_VERSION,
+            });
+        }
+        if bytes.len() < header_size {
+            return Err(MeshCacheError::Truncated);
+        }
+        let header: MeshHeader = bytemuck::pod_read_unaligned(&bytes[..header_size]);
+
+        if (
+            header.vertex_stride,
+            header.attribute_count,
+            header.attributes,
+        ) != MeshHeader::vertex_layout()
+        {
+            return Err(MeshCacheError::LayoutMismatch);
+        }
+        if header.index_size != 2 && header.index_size != 4 {
+            return Err(MeshCacheError::NotAMeshCache);
+        }
+
+        let vertex_end = header_size + header.vertex_count as usize * header.vertex_stride as usize;
+        let index_end = vertex_end + header.index_count as usize * header.index_size as usize;
+        let lod_end = index_end + header.lod_count as usize * 8;
+        if bytes.len() < lod_end {
+            return Err(MeshCacheError::Truncated);
+        }
+        let lods: Vec<Lod> = bytes[index_end..lod_end]
+            .chunks_exact(8)
+            .map(|lod| {
+                let [first_index, index_count]: [u32; 2] = bytemuck::pod_read_unaligned(lod);
+                Lod {
+                    first_index,
+                    index_count,
+                }
+            })
+            .collect();
+        let in_bounds = |lod: &Lod| {
+            lod.first_index as u64 + lod.index_count as u64 <= header.index_count as u64
+        };
+        if lods.is_empty() || !lods.iter().all(in_bounds) {
+            return Err(MeshCacheError::NotAMeshCache);
+        }
+
+        // a corrupt file is turned away here rather than drawn with stray indices
+        let index_bytes = &bytes[vertex_end..index_end];
+        let out_of_range = if header.index_size == 2 {
+            index_bytes
+                .chunks_exact(2)
+                .map(|i| u16::from_ne_bytes([i[0], i[1]]) as u32)
+                .find(|&i| i >= header.vertex_count)
+        } else {
+            index_bytes
+                .chunks_exact(4)
+                .map(|i| u32::from_ne_bytes([i[0], i[1], i[2], i[3]]))
+                .find(|&i| i >= header.vertex_count)
+        };
+        if let Some(index) = out_of_range {
+            return Err(MeshCacheError::IndexOutOfRange {
+                index,
+                vertex_count: header.vertex_count,
+            });
+        }
+
+        Ok(CachedMesh {
+            source_hash: header.source_hash,
+            aabb: Aabb {
+                min: Vec3::from_array(header.bounds_min),
+                max: Vec3::from_array(header.bounds_max),
+            },
+            bounding_sphere: BoundingSphere {
+                center: Vec3::from_slice(&header.bounding_sphere),
+                radius: header.bounding_sphere[3],
+            },
+            index_size: header.index_size,
+            vertex_count: header.vertex_count,
+            index_count: header.index_count,
+            vertex_bytes: bytes[header_size..vertex_end].to_vec(),
+            index_bytes: index_bytes.to_vec(),
+            lods,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<CachedMesh, MeshCacheError> {
+        let bytes = std::fs::read(path).map_err(|e| MeshCacheError::Io(path.to_path_buf(), e))?;
+        CachedMesh::from_bytes(&bytes)
+    }
+
+    /// writes the file, making its folder if needed
+    pub fn write(&self, path: &Path) -> Result<(), MeshCacheError> {
+        let io_error = |e| MeshCacheError::Io(path.to_path_buf(), e);
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder).map_err(io_error)?;
+        }
+        std::fs::write(path, self.to_bytes()).map_err(io_error)
+    }
+
+    ///
+    /// reads the cache of an obj file if it is still up to date, otherwise
+    /// parses the obj and writes a fresh cache. failing to write the cache
+    /// is only logged, the mesh is still returned.
+    /// a .mesh path is read as it is
+    ///
+    pub fn load_or_convert(source: &str) -> Result<CachedMesh, MeshCacheError> {
+        if Path::new(source).extension().is_some_and(|e| e == "mesh") {
+            return CachedMesh::read(Path::new(source));
+        }
+        CachedMesh::load_or_convert_at(source, &CachedMesh::cache_path_for(source))
+    }
+
+    /// load_or_convert with the cache at `cache_path` rather than in cache_dir
+    pub fn load_or_convert_at(
+        source: &str,
+        cache_path: &Path,
+    ) -> Result<CachedMesh, MeshCacheError> {
+        let bytes =
+            std::fs::read(source).map_err(|e| MeshCacheError::Io(PathBuf::from(source), e))?;
+        let source_hash = CachedMesh::hash_source(&bytes);
+
+        match CachedMesh::read(cache_path) {
+            Ok(mesh) if mesh.source_hash == source_hash => return Ok(mesh),
+            Ok(_) => log::info!("{} is out of date, rebuilding", cache_path.display()),
+            Err(MeshCacheError::Io(..)) => {}
+            Err(e) => log::info!("{}: {}, rebuilding", cache_path.display(), e),
+        }
+
+        let data = obj_parser::load_obj_file(source)?;
+        let mesh = CachedMesh::from_obj_data(&data, source_hash);
+        if let Err(e) = mesh.write(cache_path) {
+            log::warn!("can not write mesh cache {}", e);
+        }
+        Ok(mesh)
+    }
+}
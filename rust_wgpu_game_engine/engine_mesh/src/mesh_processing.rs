@@ -0,0 +1,214 @@
+///
+/// mesh post processing - normal and tangent generation over ObjData
+///
+/// everything here is plain cpu maths on the flat ObjData arrays, so it can
+/// run on a worker thread or in the build script. both functions return a new
+/// ObjData because vertices may have to be split: a hard edge needs two
+/// normals at the same position, a uv mirror seam needs two tangents
+///
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::obj_parser::ObjData;
+
+/// how generate_normals shades a mesh
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NormalMode {
+    /// every face gets its own normal, the mesh looks faceted
+    Flat,
+    /// faces meeting at less than `crease_angle` (radians) are smoothed
+    /// together, sharper edges stay hard
+    Smooth { crease_angle: f32 },
+}
+
+impl Default for NormalMode {
+    fn default() -> Self {
+        NormalMode::Smooth {
+            crease_angle: 60.0f32.to_radians(),
+        }
+    }
+}
+
+// position / uv / normal of one vertex
+fn position(data: &ObjData, i: usize) -> Vec3 {
+    Vec3::from_slice(&data.vertices[i * 3..])
+}
+
+fn tex_coord(data: &ObjData, i: usize) -> Vec2 {
+    data.texture
+        .get(i * 2..i * 2 + 2)
+        .map_or(Vec2::ZERO, Vec2::from_slice)
+}
+
+fn normal(data: &ObjData, i: usize) -> Vec3 {
+    data.normal
+        .get(i * 3..i * 3 + 3)
+        .map_or(Vec3::Y, Vec3::from_slice)
+}
+
+/// angle of a triangle at `corner`, used to weight its face against the others
+fn corner_angle(points: [Vec3; 3], corner: usize) -> f32 {
+    let p = points[corner];
+    let a = (points[(corner + 1) % 3] - p).normalize_or_zero();
+    let b = (points[(corner + 2) % 3] - p).normalize_or_zero();
+    a.dot(b).clamp(-1.0, 1.0).acos()
+}
+
+/// copies vertex `from` of `source` onto the end of `target`
+fn push_vertex(
+    target: &mut ObjData,
+    source: &ObjData,
+    from: usize,
+    normal: Vec3,
+    tangent: Option<Vec4>,
+) {
+    target
+        .vertices
+        .extend_from_slice(&position(source, from).to_array());
+    target
+        .texture
+        .extend_from_slice(&tex_coord(source, from).to_array());
+    target.normal.extend_from_slice(&normal.to_array());
+    if let Some(tangent) = tangent {
+        target.tangent.extend_from_slice(&tangent.to_array());
+    }
+}
+
+impl ObjData {
+    ///
+    /// works out vertex normals from the faces. smooth normals are weighted
+    /// by the angle each face makes at the vertex, so how a surface is split
+    /// into triangles does not change its shading. vertices that only differ
+    /// in uv (texture seams) are still smoothed together. tangents are dropped
+    /// as they depend on the normals
+    ///
+    pub fn generate_normals(&self, mode: NormalMode) -> ObjData {
+        let triangles: Vec<[usize; 3]> = self
+            .indice
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+        let face_normals: Vec<Vec3> = triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| position(self, i));
+                (b - a).cross(c - a).normalize_or_zero()
+            })
+            .collect();
+
+        // faces around each position, with the angle they make there
+        let mut around: HashMap<[u32; 3], Vec<(usize, f32)>> = HashMap::new();
+        for (face, triangle) in triangles.iter().enumerate() {
+            let points = triangle.map(|i| position(self, i));
+            for (corner, point) in points.iter().enumerate() {
+                around
+                    .entry(point.to_array().map(f32::to_bits))
+                    .or_default()
+                    .push((face, corner_angle(points, corner)));
+            }
+        }
+
+        let mut result = ObjData::default();
+        let mut split: HashMap<(usize, [u32; 3]), u32> = HashMap::new();
+        for (face, triangle) in triangles.iter().enumerate() {
+            let face_normal = face_normals[face];
+            for &vertex in triangle {
+                let normal = match mode {
+                    NormalMode::Flat => face_normal,
+                    NormalMode::Smooth { crease_angle } => {
+                        let min_cos = crease_angle.cos();
+                        let key = position(self, vertex).to_array().map(f32::to_bits);
+                        around[&key]
+                            .iter()
+                            .filter(|(other, _)| face_normals[*other].dot(face_normal) >= min_cos)
+                            .map(|(other, angle)| face_normals[*other] * *angle)
+                            .sum::<Vec3>()
+                    }
+                }
+                .normalize_or(Vec3::Y);
+
+                let key = (vertex, normal.to_array().map(f32::to_bits));
+                let index = *split.entry(key).or_insert_with(|| {
+                    push_vertex(&mut result, self, vertex, normal, None);
+                    (result.vertices.len() / 3 - 1) as u32
+                });
+                result.indice.push(index);
+            }
+        }
+        result
+    }
+
+    ///
+    /// generates tangents for normal mapping with MikkTSpace, the same tangent
+    /// space Blender, Substance and most bakers use, so baked normal maps come
+    /// out right. xyz is the tangent along +u and w is the bitangent sign, so
+    ///   bitangent = cross(normal, tangent.xyz) * tangent.w
+    /// MikkTSpace gives every corner of every face its own tangent, corners of
+    /// a vertex that got the same one are merged back together. where they
+    /// differ (uv mirror seams, hard uv edges) the vertex is split.
+    /// meshes it can not work on (no faces) get an arbitrary tangent
+    ///
+    pub fn generate_tangents(&self) -> ObjData {
+        let mut geometry = MikkGeometry {
+            data: self,
+            tangents: vec![None; self.indice.len() / 3 * 3],
+        };
+        if !mikktspace::generate_tangents(&mut geometry) {
+            log::warn!("MikkTSpace could not generate tangents, using arbitrary ones");
+        }
+
+        let mut result = ObjData::default();
+        let mut split: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+        for (corner, &vertex) in self.indice.iter().enumerate().take(geometry.tangents.len()) {
+            let n = normal(self, vertex as usize);
+            let tangent =
+                geometry.tangents[corner].unwrap_or_else(|| n.any_orthonormal_vector().extend(1.0));
+            let key = (vertex, tangent.to_array().map(f32::to_bits));
+            let index = *split.entry(key).or_insert_with(|| {
+                push_vertex(&mut result, self, vertex as usize, n, Some(tangent));
+                (result.vertices.len() / 3 - 1) as u32
+            });
+            result.indice.push(index);
+        }
+        result
+    }
+}
+
+/// ObjData as MikkTSpace sees it, one tangent is written per face corner
+struct MikkGeometry<'a> {
+    data: &'a ObjData,
+    tangents: Vec<Option<Vec4>>,
+}
+
+impl MikkGeometry<'_> {
+    fn vertex(&self, face: usize, corner: usize) -> usize {
+        self.data.indice[face * 3 + corner] as usize
+    }
+}
+
+impl mikktspace::Geometry for MikkGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.data.indice.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        position(self.data, self.vertex(face, vert)).to_array()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        normal(self.data, self.vertex(face, vert)).to_array()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        tex_coord(self.data, self.vertex(face, vert)).to_array()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = Some(Vec4::from_array(tangent));
+    }
+}
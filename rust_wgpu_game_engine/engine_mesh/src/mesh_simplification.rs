@@ -0,0 +1,420 @@
+///
+/// mesh simplification - quadric error metric LODs over ObjData
+///
+/// edges are collapsed cheapest first, the cost being how far the surviving
+/// vertex is from the planes of every face that was merged into it
+/// (Garland & Heckbert). a vertex is always collapsed onto one of its
+/// neighbours rather than to a new position, so every level of detail can
+/// index into the original vertex buffer.
+///
+/// vertices on an open border or on a seam (several vertices at one position
+/// with different uvs or normals) never move, so the outline and the texture
+/// mapping survive. vertices at one position whose uvs and normals only
+/// differ by rounding are welded into one first, they are not a seam
+///
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use glam::{DVec3, Vec3};
+
+use crate::{bounds::BoundingSphere, obj_parser::ObjData};
+
+/// how a chain of levels of detail is built
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LodSettings {
+    /// most simplified levels to add after the full detail one
+    pub levels: usize,
+    /// fraction of the triangles of the level before that each level aims for
+    pub reduction: f32,
+    /// largest error allowed, as a fraction of the mesh's bounding radius.
+    /// the chain stops early when no level can get smaller within it
+    pub max_error: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            reduction: 0.5,
+            max_error: 0.05,
+        }
+    }
+}
+
+/// one level of detail, a range of the model's index buffer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Lod {
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+impl Lod {
+    /// the ranges of index lists laid one after another in a buffer
+    pub fn ranges(lists: &[Vec<u32>]) -> Vec<Lod> {
+        let mut first_index = 0;
+        lists
+            .iter()
+            .map(|list| {
+                let lod = Lod {
+                    first_index,
+                    index_count: list.len() as u32,
+                };
+                first_index += lod.index_count;
+                lod
+            })
+            .collect()
+    }
+}
+
+/// uvs and normals closer than this are the same, vertices with them are welded
+const WELD_EPSILON: f32 = 1e-4;
+
+/// a level has to lose at least this fraction of triangles to be worth keeping
+const MIN_REDUCTION: f32 = 0.1;
+
+/// a collapse may not turn a face further than about 75 degrees, past that
+/// it starts standing on its edge and folding over its neighbours
+const MAX_TURN_COS: f64 = 0.25;
+
+/// symmetric 4x4 matrix, the sum of squared distances to a set of planes
+#[derive(Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: DVec3, d: f64) -> Quadric {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        Quadric([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// collapsing `from` onto `to` costs `cost`, versions spot entries that went stale
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    from_version: u32,
+    to_version: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // reversed, BinaryHeap pops the largest and we want the cheapest
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct Simplifier<'a> {
+    data: &'a ObjData,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    /// triangles around each vertex, dead ones are skipped when read
+    around: Vec<Vec<usize>>,
+    /// quadric of every position, shared by the vertices of a seam
+    quadrics: Vec<Quadric>,
+    group: Vec<usize>,
+    locked: Vec<bool>,
+    removed: Vec<bool>,
+    version: Vec<u32>,
+    queue: BinaryHeap<Collapse>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(data: &'a ObjData, indices: &[u32]) -> Simplifier<'a> {
+        let vertex_count = data.vertices.len() / 3;
+        let position = |i: usize| Vec3::from_slice(&data.vertices[i * 3..]);
+        let same_attributes = |a: usize, b: usize| {
+            let close = |values: &[f32], size: usize| {
+                let a = values.get(a * size..a * size + size).unwrap_or(&[]);
+                let b = values.get(b * size..b * size + size).unwrap_or(&[]);
+                a.iter().zip(b).all(|(a, b)| (a - b).abs() <= WELD_EPSILON)
+            };
+            close(&data.texture, 2) && close(&data.normal, 3)
+        };
+
+        // vertices sharing a position are one point of the surface
+        let mut groups: HashMap<[u32; 3], usize> = HashMap::new();
+        let group: Vec<usize> = (0..vertex_count)
+            .map(|i| {
+                let next = groups.len();
+                *groups
+                    .entry(position(i).to_array().map(f32::to_bits))
+                    .or_insert(next)
+            })
+            .collect();
+
+        // the ones that also share their uv and normal are one vertex, what
+        // is left of a group after welding them is a seam
+        let mut distinct: Vec<Vec<u32>> = vec![Vec::new(); groups.len()];
+        let weld: Vec<u32> = (0..vertex_count)
+            .map(|i| {
+                let kept = &mut distinct[group[i]];
+                match kept
+                    .iter()
+                    .find(|&&other| same_attributes(other as usize, i))
+                {
+                    Some(&other) => other,
+                    None => {
+                        kept.push(i as u32);
+                        i as u32
+                    }
+                }
+            })
+            .collect();
+
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]].map(|i| weld[i as usize]))
+            .filter(|t| group[t[0] as usize] != group[t[1] as usize])
+            .filter(|t| group[t[1] as usize] != group[t[2] as usize])
+            .filter(|t| group[t[2] as usize] != group[t[0] as usize])
+            .collect();
+
+        let mut around = vec![Vec::new(); vertex_count];
+        let mut quadrics = vec![Quadric::default(); distinct.len()];
+        let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+        for (index, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|i| position(i as usize).as_dvec3());
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let plane = Quadric::from_plane(normal, -normal.dot(a));
+            for (corner, &vertex) in triangle.iter().enumerate() {
+                around[vertex as usize].push(index);
+                quadrics[group[vertex as usize]].add(&plane);
+                let next = group[triangle[(corner + 1) % 3] as usize];
+                let this = group[vertex as usize];
+                *edges.entry((this.min(next), this.max(next))).or_default() += 1;
+            }
+        }
+
+        // seams and open borders stay where they are
+        let mut locked_group: Vec<bool> = distinct.iter().map(|kept| kept.len() > 1).collect();
+        for ((a, b), count) in edges {
+            if count == 1 {
+                locked_group[a] = true;
+                locked_group[b] = true;
+            }
+        }
+        let locked = group.iter().map(|&g| locked_group[g]).collect();
+
+        let mut simplifier = Simplifier {
+            data,
+            alive: vec![true; triangles.len()],
+            triangles,
+            around,
+            quadrics,
+            group,
+            locked,
+            removed: vec![false; vertex_count],
+            version: vec![0; vertex_count],
+            queue: BinaryHeap::new(),
+        };
+        for vertex in 0..vertex_count as u32 {
+            simplifier.queue_collapses(vertex);
+        }
+        simplifier
+    }
+
+    fn position(&self, vertex: u32) -> DVec3 {
+        Vec3::from_slice(&self.data.vertices[vertex as usize * 3..]).as_dvec3()
+    }
+
+    fn neighbours(&self, vertex: u32) -> Vec<u32> {
+        let mut neighbours: Vec<u32> = self.around[vertex as usize]
+            .iter()
+            .filter(|&&t| self.alive[t])
+            .flat_map(|&t| self.triangles[t])
+            .filter(|&v| v != vertex)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    fn cost(&self, from: u32, to: u32) -> f64 {
+        let mut quadric = self.quadrics[self.group[from as usize]];
+        quadric.add(&self.quadrics[self.group[to as usize]]);
+        quadric.error(self.position(to)).max(0.0)
+    }
+
+    /// queues moving `vertex` onto each neighbour, and each neighbour onto it
+    fn queue_collapses(&mut self, vertex: u32) {
+        for neighbour in self.neighbours(vertex) {
+            for (from, to) in [(vertex, neighbour), (neighbour, vertex)] {
+                if self.locked[from as usize] {
+                    continue;
+                }
+                self.queue.push(Collapse {
+                    cost: self.cost(from, to),
+                    from,
+                    to,
+                    from_version: self.version[from as usize],
+                    to_version: self.version[to as usize],
+                });
+            }
+        }
+    }
+
+    /// false if moving `from` onto `to` would turn any remaining face too far
+    fn keeps_orientation(&self, from: u32, to: u32) -> bool {
+        let target = self.position(to);
+        self.around[from as usize]
+            .iter()
+            .filter(|&&t| self.alive[t] && !self.triangles[t].contains(&to))
+            .all(|&t| {
+                let corners = self.triangles[t].map(|v| self.position(v));
+                let moved =
+                    self.triangles[t].map(|v| if v == from { target } else { self.position(v) });
+                let before = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+                let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+                before.dot(after) > MAX_TURN_COS * before.length() * after.length()
+            })
+    }
+
+    fn collapse(&mut self, from: u32, to: u32) -> usize {
+        let mut removed = 0;
+        for t in std::mem::take(&mut self.around[from as usize]) {
+            if !self.alive[t] {
+                continue;
+            }
+            if self.triangles[t].contains(&to) {
+                self.alive[t] = false;
+                removed += 1;
+            } else {
+                for v in self.triangles[t].iter_mut() {
+                    if *v == from {
+                        *v = to;
+                    }
+                }
+                self.around[to as usize].push(t);
+            }
+        }
+        let from_quadric = self.quadrics[self.group[from as usize]];
+        self.quadrics[self.group[to as usize]].add(&from_quadric);
+        self.removed[from as usize] = true;
+        self.version[to as usize] += 1;
+        for neighbour in self.neighbours(to) {
+            self.version[neighbour as usize] += 1;
+        }
+        self.queue_collapses(to);
+        for neighbour in self.neighbours(to) {
+            self.queue_collapses(neighbour);
+        }
+        removed
+    }
+
+    fn run(mut self, target_triangles: usize, max_cost: f64) -> Vec<u32> {
+        let mut triangle_count = self.triangles.len();
+        while triangle_count > target_triangles {
+            let Some(next) = self.queue.pop() else {
+                break;
+            };
+            let (from, to) = (next.from as usize, next.to as usize);
+            if self.removed[from]
+                || self.removed[to]
+                || self.version[from] != next.from_version
+                || self.version[to] != next.to_version
+            {
+                continue;
+            }
+            if next.cost > max_cost {
+                break;
+            }
+            if !self.keeps_orientation(next.from, next.to) {
+                continue;
+            }
+            triangle_count -= self.collapse(next.from, next.to);
+        }
+
+        self.triangles
+            .iter()
+            .zip(&self.alive)
+            .filter(|(_, alive)| **alive)
+            .flat_map(|(triangle, _)| *triangle)
+            .collect()
+    }
+}
+
+impl ObjData {
+    /// radius of the bounding sphere, wherever the mesh is
+    fn radius(&self) -> f32 {
+        BoundingSphere::from_points(self.vertices.chunks_exact(3).map(Vec3::from_slice)).radius
+    }
+
+    ///
+    /// a simplified copy of `indices` (triangles into this mesh's vertices)
+    /// with about `target_triangles` left. stops early rather than move the
+    /// surface further than `max_error` (in model units)
+    ///
+    pub fn simplify(&self, indices: &[u32], target_triangles: usize, max_error: f32) -> Vec<u32> {
+        let max_cost = (max_error as f64).powi(2);
+        Simplifier::new(self, indices).run(target_triangles, max_cost)
+    }
+
+    ///
+    /// index lists for a chain of levels of detail, all into this mesh's
+    /// vertices. the first is the full mesh, each one after is simplified
+    /// from the one before it
+    ///
+    pub fn generate_lods(&self, settings: &LodSettings) -> Vec<Vec<u32>> {
+        let max_error = settings.max_error * self.radius();
+        let mut lods = vec![self.indice.clone()];
+        for _ in 0..settings.levels {
+            let previous = &lods[lods.len() - 1];
+            let triangles = previous.len() / 3;
+            let target = (triangles as f32 * settings.reduction) as usize;
+            let simplified = self.simplify(previous, target, max_error);
+            if simplified.len() / 3 > (triangles as f32 * (1.0 - MIN_REDUCTION)) as usize {
+                break;
+            }
+            lods.push(simplified);
+        }
+        lods
+    }
+}
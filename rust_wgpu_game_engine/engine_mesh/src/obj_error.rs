@@ -0,0 +1,84 @@
+///
+/// ObjError - describes why a wavefront object file could not be loaded
+///
+use std::fmt;
+
+/// the kind of failure we hit while reading an .obj file
+#[derive(Debug)]
+pub enum ObjErrorKind {
+    /// the file could not be opened
+    MissingFile(std::io::Error),
+    /// the file was opened but reading a line failed
+    Io(std::io::Error),
+    /// a coordinate was missing or was not a valid float
+    MalformedFloat(String),
+    /// a face index was missing or was not a valid integer
+    MalformedIndex(String),
+    /// a face referenced an element that does not exist
+    IndexOutOfRange { index: i64, len: usize },
+    /// a face had fewer than three corners
+    FaceTooSmall(usize),
+    /// some corners of a face had a texture coordinate index and others did not
+    MissingTextureCoordinate,
+    /// some corners of a face had a normal index and others did not
+    MissingNormal,
+}
+
+/// error returned by the obj loader
+/// line is 1 based, 0 means the error is not tied to a line (eg missing file)
+#[derive(Debug)]
+pub struct ObjError {
+    pub path: String,
+    pub line: usize,
+    pub kind: ObjErrorKind,
+}
+
+impl ObjError {
+    pub fn new(path: &str, line: usize, kind: ObjErrorKind) -> Self {
+        Self {
+            path: path.to_string(),
+            line,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ObjErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjErrorKind::MissingFile(e) => write!(f, "missing object file ({})", e),
+            ObjErrorKind::Io(e) => write!(f, "failed to read line ({})", e),
+            ObjErrorKind::MalformedFloat(value) => write!(f, "malformed float '{}'", value),
+            ObjErrorKind::MalformedIndex(value) => write!(f, "malformed index '{}'", value),
+            ObjErrorKind::IndexOutOfRange { index, len } => {
+                write!(f, "index {} out of range (have {})", index, len)
+            }
+            ObjErrorKind::FaceTooSmall(count) => {
+                write!(f, "face has {} corners, need at least 3", count)
+            }
+            ObjErrorKind::MissingTextureCoordinate => {
+                write!(f, "face is missing its texture coordinate")
+            }
+            ObjErrorKind::MissingNormal => write!(f, "face is missing its normal"),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path, self.kind)
+        } else {
+            write!(f, "{}:{}: {}", self.path, self.line, self.kind)
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ObjErrorKind::MissingFile(e) | ObjErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,614 @@
+///
+/// obj parser - reads wavefront .obj files into ObjData
+///
+/// plain cpu code with no gpu types, so it can run on a worker thread or in
+/// the build script. the Loader uploads what comes out of it
+///
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bounds::{Aabb, BoundingSphere},
+    material::Material,
+    mesh_processing::NormalMode,
+    obj_error::{ObjError, ObjErrorKind},
+};
+
+/// vertext data struct implementation
+/// this is used for loading in to arrays
+/// from .obj file so that they can be used to create a
+/// rawmodel
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjData {
+    pub vertices: Vec<f32>,
+    pub normal: Vec<f32>,
+    pub texture: Vec<f32>,
+    pub indice: Vec<u32>,
+    /// x y z w per vertex, empty until generate_tangents is called
+    pub tangent: Vec<f32>,
+}
+
+impl ObjData {
+    /// interlaces the flat position / texture / normal arrays into vertices
+    pub fn to_vertices(&self) -> Vec<Vertex> {
+        let num_vertices = self.vertices.len() / 3;
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
+
+        for i in 0..num_vertices {
+            vertices.push(Vertex {
+                position: [
+                    self.vertices[i * 3],
+                    self.vertices[i * 3 + 1],
+                    self.vertices[i * 3 + 2],
+                ],
+                tex_coords: [self.texture[i * 2], self.texture[i * 2 + 1]],
+                normal: [
+                    self.normal[i * 3],
+                    self.normal[i * 3 + 1],
+                    self.normal[i * 3 + 2],
+                ],
+            });
+        }
+        vertices
+    }
+
+    /// interlaces the arrays into vertices for normal mapping.
+    /// without tangents (see generate_tangents) +x is used for every vertex
+    pub fn to_tangent_vertices(&self) -> Vec<TangentVertex> {
+        self.to_vertices()
+            .into_iter()
+            .enumerate()
+            .map(|(i, vertex)| TangentVertex {
+                position: vertex.position,
+                tex_coords: vertex.tex_coords,
+                normal: vertex.normal,
+                tangent: self
+                    .tangent
+                    .get(i * 4..i * 4 + 4)
+                    .map_or([1.0, 0.0, 0.0, 1.0], |t| [t[0], t[1], t[2], t[3]]),
+            })
+            .collect()
+    }
+}
+
+///
+/// one mesh of an object file - all the faces that share a material
+///
+pub struct ObjMesh {
+    /// the name usemtl gave, kept when no library defines it
+    pub material_name: Option<String>,
+    pub material: Option<Material>,
+    pub data: ObjData,
+}
+
+///
+/// everything read from an object file before it is turned into ObjData.
+/// groups hold the triangles for each material, in the order materials were first used
+///
+struct ParsedObj {
+    vertices: Vec<glam::Vec3>,
+    textures: Vec<glam::Vec2>,
+    normals: Vec<glam::Vec3>,
+    /// flat normals of the faces that have no vn, kept apart so they do not
+    /// shift the negative vn indices or hide that the file has no normals
+    face_normals: Vec<glam::Vec3>,
+    groups: Vec<(Option<String>, Vec<[FaceCorner; 3]>)>,
+    material_libraries: Vec<PathBuf>,
+}
+
+///
+/// one corner of a face, indices are 0 based and already resolved
+///
+#[derive(Copy, Clone, Debug)]
+struct FaceCorner {
+    vertex: usize,
+    texture: Option<usize>,
+    normal: Option<usize>,
+    /// into ParsedObj::face_normals, for faces without normals
+    face_normal: Option<usize>,
+}
+
+impl FaceCorner {
+    /// the v/vt/vn style key used to find unique combinations.
+    /// face normals only split corners when `face_normals` is set
+    fn key(&self, face_normals: bool) -> String {
+        let optional = |index: Option<usize>| index.map(|i| i.to_string()).unwrap_or_default();
+        let normal = match (self.normal, self.face_normal) {
+            (Some(normal), _) => normal.to_string(),
+            (None, Some(face)) if face_normals => format!("f{}", face),
+            _ => String::new(),
+        };
+        format!("{}/{}/{}", self.vertex, optional(self.texture), normal)
+    }
+}
+
+///
+/// stringmap implementation
+/// simple key based lookup -
+#[derive(Default)]
+pub struct StringMap {
+    internal_map: HashMap<String, usize>,
+}
+
+impl StringMap {
+    pub fn new() -> Self {
+        Self {
+            internal_map: HashMap::new(),
+        }
+    }
+
+    /// adds a string to the map and returns its index
+    /// if the string is not in the map, it adds it
+    pub fn add(&mut self, index: &str) -> usize {
+        let result = self.internal_map.get_key_value(index);
+        match result {
+            Some(t) => *t.1,
+            None => {
+                let map_size = self.internal_map.len();
+                self.internal_map.insert(index.to_string(), map_size);
+                map_size
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.internal_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.internal_map.is_empty()
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+}
+
+///
+/// Vertex with a tangent, for normal mapped shaders.
+/// tangent.w is the bitangent sign: bitangent = cross(normal, tangent.xyz) * w
+///
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TangentVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+}
+
+///
+/// helper function to split strings into a vector by whitespace
+///
+fn split_string_to_vec(input: &str) -> Vec<String> {
+    input.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+fn split_string_to_vec_using_slash(input: &str) -> Vec<String> {
+    input.split('/').map(|s| s.to_string()).collect()
+}
+
+///
+/// helper to parse the float at position `index` of a split line
+///
+fn parse_float(
+    current_line: &[String],
+    index: usize,
+    filename: &str,
+    line_number: usize,
+) -> Result<f32, ObjError> {
+    let value = current_line.get(index).map(|s| s.as_str()).unwrap_or("");
+    value.parse::<f32>().map_err(|_| {
+        ObjError::new(
+            filename,
+            line_number,
+            ObjErrorKind::MalformedFloat(value.to_string()),
+        )
+    })
+}
+
+///
+/// helper to resolve an obj index into a 0 based index into `len` elements
+/// positive indices are 1 based, negative indices count back from the
+/// most recently read element (-1 is the last one)
+///
+fn parse_index(
+    value: &str,
+    len: usize,
+    filename: &str,
+    line_number: usize,
+) -> Result<usize, ObjError> {
+    let index = value.parse::<i64>().map_err(|_| {
+        ObjError::new(
+            filename,
+            line_number,
+            ObjErrorKind::MalformedIndex(value.to_string()),
+        )
+    })?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if index == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(ObjError::new(
+            filename,
+            line_number,
+            ObjErrorKind::IndexOutOfRange { index, len },
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+///
+/// parses one corner of a face - v, v/vt, v//vn or v/vt/vn
+///
+fn parse_face_corner(
+    item: &str,
+    counts: (usize, usize, usize),
+    filename: &str,
+    line_number: usize,
+) -> Result<FaceCorner, ObjError> {
+    let current_indice = split_string_to_vec_using_slash(item);
+    // first item = vertex, 2nd is texture and third is normal
+    let vertex = parse_index(&current_indice[0], counts.0, filename, line_number)?;
+    let texture = match current_indice.get(1) {
+        Some(t) if !t.is_empty() => Some(parse_index(t, counts.1, filename, line_number)?),
+        _ => None,
+    };
+    let normal = match current_indice.get(2) {
+        Some(n) if !n.is_empty() => Some(parse_index(n, counts.2, filename, line_number)?),
+        _ => None,
+    };
+    Ok(FaceCorner {
+        vertex,
+        texture,
+        normal,
+        face_normal: None,
+    })
+}
+
+///
+/// newell's method - gives a stable normal for any (even concave or slightly
+/// non planar) polygon. length is zero for degenerate polygons
+///
+fn polygon_normal(points: &[glam::Vec3]) -> glam::Vec3 {
+    let mut normal = glam::Vec3::ZERO;
+    for i in 0..points.len() {
+        let current = points[i];
+        let next = points[(i + 1) % points.len()];
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal
+}
+
+///
+/// splits a polygon into triangles using ear clipping, so concave faces are
+/// handled. returned indices point into `points` and keep the polygon winding.
+/// falls back to a fan if the polygon is degenerate
+///
+pub fn triangulate(points: &[glam::Vec3]) -> Vec<[usize; 3]> {
+    let fan = |remaining: &[usize]| -> Vec<[usize; 3]> {
+        (1..remaining.len() - 1)
+            .map(|i| [remaining[0], remaining[i], remaining[i + 1]])
+            .collect()
+    };
+
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let normal = polygon_normal(points);
+    if points.len() == 3 || normal.length_squared() <= f32::EPSILON {
+        return fan(&(0..points.len()).collect::<Vec<_>>());
+    }
+
+    // project onto the plane of the polygon so we can work in 2d
+    let normal = normal.normalize();
+    let tangent = normal.any_orthonormal_vector();
+    let bitangent = normal.cross(tangent);
+    let flat: Vec<glam::Vec2> = points
+        .iter()
+        .map(|p| glam::Vec2::new(p.dot(tangent), p.dot(bitangent)))
+        .collect();
+
+    // with this basis the polygon is always counter clockwise in 2d
+    let cross = |a: glam::Vec2, b: glam::Vec2, c: glam::Vec2| (b - a).perp_dot(c - a);
+    let inside = |p: glam::Vec2, a: glam::Vec2, b: glam::Vec2, c: glam::Vec2| {
+        cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+    };
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let prev = remaining[(i + count - 1) % count];
+            let current = remaining[i];
+            let next = remaining[(i + 1) % count];
+            let (a, b, c) = (flat[prev], flat[current], flat[next]);
+            if cross(a, b, c) <= 0.0 {
+                // reflex or collinear corner, can not be an ear
+                return false;
+            }
+            !remaining
+                .iter()
+                .filter(|&&other| other != prev && other != current && other != next)
+                .any(|&other| inside(flat[other], a, b, c))
+        });
+
+        match ear {
+            Some(i) => {
+                let prev = remaining[(i + count - 1) % count];
+                let next = remaining[(i + 1) % count];
+                triangles.push([prev, remaining[i], next]);
+                remaining.remove(i);
+            }
+            None => {
+                // self intersecting or otherwise odd polygon - do the best we can
+                triangles.extend(fan(&remaining));
+                return triangles;
+            }
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+///
+/// reads every line of a wavefront object file into a ParsedObj.
+/// faces can be any polygon and use any of the v, v/vt, v//vn or v/vt/vn forms,
+/// they are triangulated and sorted by the material active when they were read
+///
+fn parse_obj_file(filename: &str) -> Result<ParsedObj, ObjError> {
+    // lets open our file and start reading through it.
+    let file = File::open(filename)
+        .map_err(|e| ObjError::new(filename, 0, ObjErrorKind::MissingFile(e)))?;
+    let reader = BufReader::new(file);
+
+    // create temporrary structures for holding data we read in from
+    // model file (obj)
+    let mut parsed = ParsedObj {
+        vertices: Vec::new(),
+        textures: Vec::new(),
+        normals: Vec::new(),
+        face_normals: Vec::new(),
+        groups: vec![(None, Vec::new())],
+        material_libraries: Vec::new(),
+    };
+    let mut current_group = 0;
+
+    // Iterate over the lines of the file
+    for (line_index, line_from_file) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line_from_file
+            .map_err(|e| ObjError::new(filename, line_number, ObjErrorKind::Io(e)))?;
+        if line.is_empty() {
+            continue;
+        }
+        let current_line = split_string_to_vec(&line);
+        // the keyword can be followed by spaces or tabs
+        let keyword = current_line.first().map_or("", |k| k.as_str());
+
+        // check to see if we have vector co-ordinates
+        if keyword == "v" {
+            let x = parse_float(&current_line, 1, filename, line_number)?;
+            let y = parse_float(&current_line, 2, filename, line_number)?;
+            let z = parse_float(&current_line, 3, filename, line_number)?;
+            parsed.vertices.push(glam::Vec3::new(x, y, z));
+        }
+        if keyword == "vt" {
+            let x = parse_float(&current_line, 1, filename, line_number)?;
+            // v is optional, 1d textures only give u
+            let y = match current_line.get(2) {
+                Some(_) => parse_float(&current_line, 2, filename, line_number)?,
+                None => 0.0,
+            };
+            parsed.textures.push(glam::Vec2::new(x, y));
+        }
+
+        if keyword == "vn" {
+            let x = parse_float(&current_line, 1, filename, line_number)?;
+            let y = parse_float(&current_line, 2, filename, line_number)?;
+            let z = parse_float(&current_line, 3, filename, line_number)?;
+            parsed.normals.push(glam::Vec3::new(x, y, z));
+        }
+        if keyword == "mtllib" {
+            // libraries are relative to the obj file
+            let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+            for library in current_line.iter().skip(1) {
+                parsed.material_libraries.push(directory.join(library));
+            }
+        }
+        if keyword == "usemtl" {
+            // faces using the same material end up in the same group,
+            // wherever they are in the file
+            let name = current_line[1..].join(" ");
+            current_group = match parsed
+                .groups
+                .iter()
+                .position(|(material, _)| material.as_deref() == Some(name.as_str()))
+            {
+                Some(group) => group,
+                None => {
+                    parsed.groups.push((Some(name), Vec::new()));
+                    parsed.groups.len() - 1
+                }
+            };
+        }
+        if keyword == "f" {
+            // negative indices are relative to what has been read so far,
+            // so corners have to be resolved as we go
+            let counts = (
+                parsed.vertices.len(),
+                parsed.textures.len(),
+                parsed.normals.len(),
+            );
+            let mut corners = Vec::with_capacity(current_line.len() - 1);
+            for entry in current_line.iter().skip(1) {
+                corners.push(parse_face_corner(entry, counts, filename, line_number)?);
+            }
+            if corners.len() < 3 {
+                return Err(ObjError::new(
+                    filename,
+                    line_number,
+                    ObjErrorKind::FaceTooSmall(corners.len()),
+                ));
+            }
+
+            // a face either gives every corner a texture coordinate / normal or none
+            let with_texture = corners.iter().filter(|c| c.texture.is_some()).count();
+            if with_texture != 0 && with_texture != corners.len() {
+                return Err(ObjError::new(
+                    filename,
+                    line_number,
+                    ObjErrorKind::MissingTextureCoordinate,
+                ));
+            }
+            let with_normal = corners.iter().filter(|c| c.normal.is_some()).count();
+            if with_normal != 0 && with_normal != corners.len() {
+                return Err(ObjError::new(
+                    filename,
+                    line_number,
+                    ObjErrorKind::MissingNormal,
+                ));
+            }
+
+            let points: Vec<glam::Vec3> =
+                corners.iter().map(|c| parsed.vertices[c.vertex]).collect();
+            if with_normal == 0 {
+                // no normals in the file for this face, use a flat face normal
+                parsed
+                    .face_normals
+                    .push(polygon_normal(&points).normalize_or(glam::Vec3::Y));
+                let face_normal = parsed.face_normals.len() - 1;
+                for corner in corners.iter_mut() {
+                    corner.face_normal = Some(face_normal);
+                }
+            }
+            let triangles = &mut parsed.groups[current_group].1;
+            for [a, b, c] in triangulate(&points) {
+                triangles.push([corners[a], corners[b], corners[c]]);
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+///
+/// builds indexed model data out of triangles from a ParsedObj.
+/// corners without a texture coordinate get (0, 0)
+///
+fn build_obj_data<'a>(
+    parsed: &ParsedObj,
+    triangles_to_process: impl Iterator<Item = &'a [FaceCorner; 3]>,
+) -> ObjData {
+    // create our vectors for final model data
+    let mut our_model = ObjData::default();
+
+    // files exported without vn lines get smooth normals worked out for them,
+    // otherwise the odd face without normals keeps its flat face normal
+    let generate_normals = parsed.normals.is_empty();
+
+    // now we start mapping
+    // now process indexices.  for each unique combination of indexices
+    // we will build the vertices/normals and texture coordinates
+    // the map hands out indices in the order combinations are first seen
+    let mut my_map = StringMap::new();
+
+    // now we store results into indidvual arrays in the correct order
+    // each unique combination becomes one vertex, repeats just reuse its index
+    for corner in triangles_to_process.flatten() {
+        let index = my_map.add(&corner.key(!generate_normals));
+        our_model.indice.push(index as u32);
+        if index < our_model.vertices.len() / 3 {
+            continue;
+        }
+        let position = parsed.vertices[corner.vertex];
+        let texture = corner
+            .texture
+            .map(|t| parsed.textures[t])
+            .unwrap_or(glam::Vec2::ZERO);
+        let normal = match (corner.normal, corner.face_normal) {
+            (Some(normal), _) => parsed.normals[normal],
+            (None, Some(face)) => parsed.face_normals[face],
+            (None, None) => glam::Vec3::Y,
+        };
+        our_model.vertices.extend_from_slice(&position.to_array());
+        our_model.texture.extend_from_slice(&texture.to_array());
+        our_model.normal.extend_from_slice(&normal.to_array());
+    }
+
+    if generate_normals {
+        our_model = our_model.generate_normals(NormalMode::default());
+    }
+    our_model
+}
+
+///
+/// Object loader reads in a wavefront object file and creates a raw model using the data
+/// every face ends up in the one model, materials are ignored
+///
+pub fn load_obj_file(filename: &str) -> Result<ObjData, ObjError> {
+    let parsed = parse_obj_file(filename)?;
+    let triangles = parsed.groups.iter().flat_map(|(_, triangles)| triangles);
+    Ok(build_obj_data(&parsed, triangles))
+}
+
+///
+/// loads an object file split into one mesh per material.
+/// materials come from the file's mtllib libraries, faces read before any
+/// usemtl (or using a material no library defines) get no material.
+/// a library that is missing or can not be read is skipped with a warning
+///
+pub fn load_obj_meshes(filename: &str) -> Result<Vec<ObjMesh>, ObjError> {
+    let parsed = parse_obj_file(filename)?;
+
+    let mut materials = HashMap::new();
+    for library in &parsed.material_libraries {
+        match Material::load_mtl_file(&library.to_string_lossy()) {
+            Ok(library) => materials.extend(library),
+            Err(e) => log::warn!("{}: {}", filename, e),
+        }
+    }
+
+    let mut meshes = Vec::new();
+    for (material_name, triangles) in &parsed.groups {
+        if triangles.is_empty() {
+            continue;
+        }
+        let material = material_name.as_ref().and_then(|name| {
+            let material = materials.get(name).cloned();
+            if material.is_none() {
+                log::warn!("{}: material '{}' is not defined", filename, name);
+            }
+            material
+        });
+        meshes.push(ObjMesh {
+            material_name: material_name.clone(),
+            material,
+            data: build_obj_data(&parsed, triangles.iter()),
+        });
+    }
+    Ok(meshes)
+}
+
+/// box and sphere around the vertex positions
+pub fn vertex_bounds(vertices: &[Vertex]) -> (Aabb, BoundingSphere) {
+    let positions = vertices.iter().map(|v| glam::Vec3::from_array(v.position));
+    (
+        Aabb::from_points(positions.clone()),
+        BoundingSphere::from_points(positions),
+    )
+}
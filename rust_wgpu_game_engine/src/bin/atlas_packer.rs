@@ -0,0 +1,75 @@
+///
+/// atlas_packer - packs a folder of images into one texture atlas
+///
+/// atlas_packer <folder> <atlas.png> [--rects] [--padding <pixels>] [--max-size <pixels>]
+///
+/// without --rects every image must be the same size and they are laid out in
+/// a grid for ModelTexture::number_of_rows. the manifest is written next to
+/// the atlas with an .atlas extension. --rects atlases stay within --max-size,
+/// 8192 unless given
+///
+use rust_wgpu_game_engine::game_engine::texture_atlas::{DEFAULT_MAX_ATLAS_SIZE, TextureAtlas};
+use std::path::Path;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: atlas_packer <folder> <atlas.png> [--rects] [--padding <pixels>] [--max-size <pixels>]"
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut positional = Vec::new();
+    let mut rects = false;
+    let mut padding = 0;
+    let mut max_size = DEFAULT_MAX_ATLAS_SIZE;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rects" => rects = true,
+            "--padding" => {
+                padding = match args.next().and_then(|p| p.parse::<u32>().ok()) {
+                    Some(padding) => padding,
+                    None => usage(),
+                }
+            }
+            "--max-size" => {
+                max_size = match args.next().and_then(|p| p.parse::<u32>().ok()) {
+                    Some(max_size) => max_size,
+                    None => usage(),
+                }
+            }
+            _ if arg.starts_with("--") => usage(),
+            _ => positional.push(arg),
+        }
+    }
+    let [folder, output] = positional.as_slice() else {
+        usage();
+    };
+
+    let result = TextureAtlas::load_folder(folder).and_then(|images| {
+        let atlas = if rects {
+            TextureAtlas::pack_rects(&images, padding, max_size)?
+        } else {
+            TextureAtlas::pack_grid(&images)?
+        };
+        let image_path = Path::new(output);
+        let manifest_path = image_path.with_extension("atlas");
+        atlas.save(image_path, &manifest_path)?;
+        println!(
+            "packed {} images into {} ({}x{}), manifest {}",
+            images.len(),
+            image_path.display(),
+            atlas.image.width(),
+            atlas.image.height(),
+            manifest_path.display()
+        );
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        eprintln!("atlas_packer: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,40 @@
+///
+/// mesh_converter - converts obj files into binary .mesh caches
+///
+/// mesh_converter <input.obj> [output.mesh]
+///
+/// without an output path the cache is written to CachedMesh::cache_path_for,
+/// which is where Loader::load_3d_model_cached and the background loader look for it
+///
+use rust_wgpu_game_engine::game_engine::mesh_cache::CachedMesh;
+use std::path::PathBuf;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (input, output) = match args.as_slice() {
+        [input] => (input, CachedMesh::cache_path_for(input)),
+        [input, output] if !input.starts_with("--") => (input, PathBuf::from(output)),
+        _ => {
+            eprintln!("usage: mesh_converter <input.obj> [output.mesh]");
+            std::process::exit(2);
+        }
+    };
+
+    let result = CachedMesh::convert_obj(input).and_then(|mesh| {
+        mesh.write(&output)?;
+        println!(
+            "{}: {} vertices, {} indices in {} levels of detail -> {}",
+            input,
+            mesh.vertex_count,
+            mesh.index_count,
+            mesh.lods.len(),
+            output.display()
+        );
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        eprintln!("mesh_converter: {}", e);
+        std::process::exit(1);
+    }
+}
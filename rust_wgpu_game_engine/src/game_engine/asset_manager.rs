@@ -0,0 +1,674 @@
+///
+/// AssetManager - loads each model / texture file once and hands out shared handles
+///
+/// assets are keyed by their normalised path (textures also by how they were
+/// uploaded), so loading the same file twice gives back the same gpu data.
+/// one texture handle can be put on any number of models, the renderer
+/// batches on the mesh and the texture together.
+/// an asset stays cached while anything uses it - a handle, or a copy taken
+/// out of one such as the TexturedModel on an entity - so loading or reloading
+/// the file finds the gpu data already drawn. update forgets the rest once a
+/// frame (remove_unused), and wgpu releases their buffers and textures.
+/// the caching itself is AssetCache, which needs no gpu
+///
+/// when a file changes on disk reload swaps the new gpu data into every handle
+/// of it, and hands back what changed so entities holding copies can be updated.
+///
+/// the _async loads return straight away with a pending handle, the file is
+/// decoded on a worker thread and uploaded by update, which the game calls
+/// once a frame
+///
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::game_engine::{
+    assets::ModelAsset,
+    background_loader::{BackgroundLoader, Decoded, LoadJob},
+    entity::Entity,
+    loader::Loader,
+    mesh_cache::MeshCacheError,
+    model_texture::ModelTexture,
+    raw_model::RawModel,
+    texture_data::{TextureData, TextureError, TextureOptions},
+    textured_model::TexturedModel,
+};
+
+enum Slot<T> {
+    /// still being loaded in the background
+    Pending,
+    /// the inner Arc is replaced when the asset is reloaded
+    Ready(Arc<T>),
+    Failed,
+}
+
+/// shared, reference counted handle to a loaded asset
+pub struct Handle<T> {
+    path: Arc<PathBuf>,
+    asset: Arc<RwLock<Slot<T>>>,
+}
+
+impl<T> Handle<T> {
+    fn new(path: PathBuf, asset: T) -> Self {
+        Handle::with_slot(path, Slot::Ready(Arc::new(asset)))
+    }
+
+    fn pending(path: PathBuf) -> Self {
+        Handle::with_slot(path, Slot::Pending)
+    }
+
+    fn with_slot(path: PathBuf, slot: Slot<T>) -> Self {
+        Self {
+            path: Arc::new(path),
+            asset: Arc::new(RwLock::new(slot)),
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Slot<T>> {
+        self.asset
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Slot<T>> {
+        self.asset
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// the current version of the asset, None while it is loading or if it
+    /// failed. a reload does not change a copy that has already been taken
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.read() {
+            Slot::Ready(asset) => Some(asset.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.read(), Slot::Ready(_))
+    }
+
+    /// the background load failed, the error has been logged
+    pub fn is_failed(&self) -> bool {
+        matches!(*self.read(), Slot::Failed)
+    }
+
+    /// puts a loaded or reloaded asset in, returning the one it replaced
+    fn replace(&self, asset: T) -> Option<Arc<T>> {
+        match std::mem::replace(&mut *self.write(), Slot::Ready(Arc::new(asset))) {
+            Slot::Ready(old) => Some(old),
+            _ => None,
+        }
+    }
+
+    fn set_failed(&self) {
+        *self.write() = Slot::Failed;
+    }
+
+    /// the normalised path the asset was loaded from
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// number of handles sharing this asset
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.asset)
+    }
+
+    /// true when both handles point at the same loaded asset
+    pub fn ptr_eq(&self, other: &Handle<T>) -> bool {
+        Arc::ptr_eq(&self.asset, &other.asset)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            asset: self.asset.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({})", self.path.display())
+    }
+}
+
+/// an uploaded asset whose copies the manager can count. every copy taken
+/// of it shares the one `users` Arc
+pub trait Upload {
+    fn users(&self) -> &Arc<()>;
+}
+
+impl Upload for RawModel {
+    fn users(&self) -> &Arc<()> {
+        &self.users
+    }
+}
+
+impl Upload for ModelTexture {
+    fn users(&self) -> &Arc<()> {
+        &self.users
+    }
+}
+
+/// something besides the manager holds the handle, the asset or a copy of it
+fn is_in_use<T: Upload>(handle: &Handle<T>) -> bool {
+    handle.ref_count() > 1
+        || match &*handle.read() {
+            Slot::Ready(asset) => {
+                Arc::strong_count(asset) > 1 || Arc::strong_count(asset.users()) > 1
+            }
+            _ => false,
+        }
+}
+
+///
+/// the handles of one kind of asset, keyed by path (and for textures how they
+/// were uploaded). a key hands back the same handle for as long as anything
+/// uses it. nothing here touches the gpu, the loading is up to the caller
+///
+pub struct AssetCache<K, T> {
+    handles: HashMap<K, Handle<T>>,
+}
+
+impl<K, T> Default for AssetCache<K, T> {
+    fn default() -> Self {
+        Self {
+            handles: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, T: Upload> AssetCache<K, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the cached handle for a key, unless its load failed and should be tried again
+    pub fn get(&self, key: &K) -> Option<Handle<T>> {
+        self.handles
+            .get(key)
+            .filter(|handle| !handle.is_failed())
+            .cloned()
+    }
+
+    ///
+    /// the cached handle for a key, or a new one holding what `load` gives.
+    /// `load` is only called when there is nothing cached, an error is
+    /// returned as it is and nothing is cached
+    ///
+    pub fn get_or_load<E>(
+        &mut self,
+        key: K,
+        path: &Path,
+        load: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Handle<T>, E> {
+        if let Some(handle) = self.get(&key) {
+            return Ok(handle);
+        }
+        let handle = Handle::new(path.to_path_buf(), load()?);
+        self.handles.insert(key, handle.clone());
+        Ok(handle)
+    }
+
+    /// the cached handle for a key, or a new pending one. true if it is new
+    /// and its load still has to be started
+    fn get_or_pending(&mut self, key: K, path: &Path) -> (Handle<T>, bool) {
+        if let Some(handle) = self.get(&key) {
+            return (handle, false);
+        }
+        let handle = Handle::pending(path.to_path_buf());
+        self.handles.insert(key, handle.clone());
+        (handle, true)
+    }
+
+    /// forgets assets that no handle, entity or other copy uses any more
+    pub fn remove_unused(&mut self) {
+        self.handles.retain(|_, handle| is_in_use(handle));
+    }
+
+    /// number of assets that are still in use
+    pub fn in_use_count(&self) -> usize {
+        self.handles
+            .values()
+            .filter(|handle| is_in_use(handle))
+            .count()
+    }
+
+    /// number of assets cached, including ones remove_unused would forget
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+}
+
+/// the assets an entity is waiting on before it can be drawn
+#[derive(Clone, Debug)]
+pub struct PendingModel {
+    pub model: Handle<RawModel>,
+    pub texture: Handle<ModelTexture>,
+    /// copied onto the texture once it is ready, see ModelTexture::number_of_rows
+    pub number_of_rows: u32,
+}
+
+impl PendingModel {
+    pub fn new(model: &Handle<RawModel>, texture: &Handle<ModelTexture>) -> Self {
+        Self {
+            model: model.clone(),
+            texture: texture.clone(),
+            number_of_rows: 1,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.model.is_ready() && self.texture.is_ready()
+    }
+
+    /// the textured model once both of its assets have been uploaded
+    pub fn get(&self) -> Option<TexturedModel> {
+        let mut texture = (*self.texture.get()?).clone();
+        texture.number_of_rows = self.number_of_rows;
+        let model = self.model.get()?;
+        Some(TexturedModel::new(&model, &texture))
+    }
+}
+
+/// how far the background loads have got, for loading screens.
+/// counts everything queued since the last time all loads had finished
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub total: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+
+    /// 0..1, finished (loaded or failed) over queued
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.loaded + self.failed) as f32 / self.total as f32
+        }
+    }
+}
+
+/// an asset that was swapped out by AssetManager::reload
+pub enum Reloaded {
+    Model {
+        old: Arc<RawModel>,
+        new: Arc<RawModel>,
+    },
+    Texture {
+        old: Arc<ModelTexture>,
+        new: Arc<ModelTexture>,
+    },
+}
+
+impl Reloaded {
+    /// points an entity at the new gpu data if it was using the old one
+    pub fn apply(&self, entity: &mut Entity) {
+        match self {
+            Reloaded::Model { old, new } => {
+                if entity.model.model.vertex_buffer == old.vertex_buffer {
+                    entity.model.model = (**new).clone();
+                }
+            }
+            Reloaded::Texture { old, new } => {
+                // entities may have changed number_of_rows etc. on their copy,
+                // so only the gpu side is swapped
+                if entity.model.texture.id == old.id {
+                    entity.model.texture.diffuse_texture_view = new.diffuse_texture_view.clone();
+                    entity.model.texture.diffuse_bind_group = new.diffuse_bind_group.clone();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReloadError {
+    Model(MeshCacheError),
+    Texture(TextureError),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Model(e) => write!(f, "{}", e),
+            ReloadError::Texture(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReloadError::Model(e) => Some(e),
+            ReloadError::Texture(e) => Some(e),
+        }
+    }
+}
+
+pub struct AssetManager {
+    loader: Loader,
+    models: AssetCache<PathBuf, RawModel>,
+    textures: AssetCache<(PathBuf, TextureOptions), ModelTexture>,
+    // started on the first background load
+    background: Option<BackgroundLoader>,
+    progress: LoadProgress,
+    placeholder: Option<TexturedModel>,
+}
+
+impl AssetManager {
+    pub fn new(loader: Loader) -> Self {
+        Self {
+            loader,
+            models: AssetCache::new(),
+            textures: AssetCache::new(),
+            background: None,
+            progress: LoadProgress::default(),
+            placeholder: None,
+        }
+    }
+
+    /// the loader used for uploads, for anything the manager does not cache
+    pub fn get_loader(&mut self) -> &mut Loader {
+        &mut self.loader
+    }
+
+    ///
+    /// makes a path usable as a cache key - absolute, with . and .. resolved.
+    /// files that exist are canonicalised so symlinks and different spellings
+    /// of the same file match
+    ///
+    pub fn normalise_path(path: &str) -> PathBuf {
+        if let Ok(canonical) = std::fs::canonicalize(path) {
+            return canonical;
+        }
+        let path = Path::new(path);
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().unwrap_or_default().join(path)
+        };
+        let mut normalised = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalised.pop();
+                }
+                other => normalised.push(other),
+            }
+        }
+        normalised
+    }
+
+    ///
+    /// loads an obj file through its mesh cache, or returns the handle of the
+    /// copy already loaded. if the file is already being loaded in the
+    /// background the pending handle is returned
+    ///
+    pub fn load_model(&mut self, path: &str) -> Result<Handle<RawModel>, MeshCacheError> {
+        let key = AssetManager::normalise_path(path);
+        self.models.get_or_load(key.clone(), &key, || {
+            self.loader.load_3d_model_cached(&key.to_string_lossy())
+        })
+    }
+
+    ///
+    /// uploads the mesh the build embedded for a model asset, so nothing is read
+    /// from disk. it is cached under the asset's source file, which is what a
+    /// reload reads
+    ///
+    pub fn load_model_asset(
+        &mut self,
+        asset: &ModelAsset,
+    ) -> Result<Handle<RawModel>, MeshCacheError> {
+        let key = AssetManager::normalise_path(&asset.full_path());
+        self.models.get_or_load(key.clone(), &key, || {
+            self.loader.load_3d_model_from_bytes(asset.mesh)
+        })
+    }
+
+    /// loads an image file, or returns the handle of the copy already uploaded
+    /// with the same options
+    pub fn load_texture(
+        &mut self,
+        path: &str,
+        options: &TextureOptions,
+    ) -> Result<Handle<ModelTexture>, TextureError> {
+        let path = AssetManager::normalise_path(path);
+        self.textures
+            .get_or_load((path.clone(), options.clone()), &path, || {
+                self.loader
+                    .load_texture_from_path(&path.to_string_lossy(), options)
+            })
+    }
+
+    fn queue(&mut self, job: LoadJob) {
+        if self.progress.is_done() {
+            self.progress = LoadProgress::default();
+        }
+        self.progress.total += 1;
+        self.background
+            .get_or_insert_with(BackgroundLoader::new)
+            .queue(job);
+    }
+
+    /// starts loading an obj file on a worker thread, the handle is pending
+    /// until update has uploaded it
+    pub fn load_model_async(&mut self, path: &str) -> Handle<RawModel> {
+        let key = AssetManager::normalise_path(path);
+        let (handle, is_new) = self.models.get_or_pending(key.clone(), &key);
+        if is_new {
+            self.queue(LoadJob::Model(key));
+        }
+        handle
+    }
+
+    /// starts loading an image file on a worker thread, the handle is pending
+    /// until update has uploaded it
+    pub fn load_texture_async(
+        &mut self,
+        path: &str,
+        options: &TextureOptions,
+    ) -> Handle<ModelTexture> {
+        let path = AssetManager::normalise_path(path);
+        let (handle, is_new) = self
+            .textures
+            .get_or_pending((path.clone(), options.clone()), &path);
+        if is_new {
+            self.queue(LoadJob::Texture(path, options.clone()));
+        }
+        handle
+    }
+
+    ///
+    /// uploads whatever the workers have finished decoding and forgets assets
+    /// nothing uses any more, call once a frame from the render thread.
+    /// failed loads are logged and their handles marked failed
+    ///
+    pub fn update(&mut self) {
+        self.remove_unused();
+        let Some(background) = &self.background else {
+            return;
+        };
+        for decoded in background.finished() {
+            let loaded = match decoded {
+                Decoded::Model { path, result } => {
+                    // nobody wants it any more, skip the upload
+                    let Some(handle) = self
+                        .models
+                        .handles
+                        .get(&path)
+                        .filter(|h| h.ref_count() > 1)
+                        .cloned()
+                    else {
+                        self.models.handles.remove(&path);
+                        self.progress.loaded += 1;
+                        continue;
+                    };
+                    match result {
+                        Ok(mesh) => {
+                            handle.replace(self.loader.upload_cached_mesh(&mesh));
+                            true
+                        }
+                        Err(e) => {
+                            log::error!("{}", e);
+                            handle.set_failed();
+                            false
+                        }
+                    }
+                }
+                Decoded::Texture {
+                    path,
+                    options,
+                    result,
+                } => {
+                    let key = (path, options);
+                    let Some(handle) = self
+                        .textures
+                        .handles
+                        .get(&key)
+                        .filter(|h| h.ref_count() > 1)
+                        .cloned()
+                    else {
+                        self.textures.handles.remove(&key);
+                        self.progress.loaded += 1;
+                        continue;
+                    };
+                    let texture = result.and_then(|levels: Vec<TextureData>| {
+                        self.loader.upload_texture_levels(&levels, &key.1)
+                    });
+                    match texture {
+                        Ok(texture) => {
+                            handle.replace(texture);
+                            true
+                        }
+                        Err(e) => {
+                            log::error!("{}: {}", key.0.display(), e);
+                            handle.set_failed();
+                            false
+                        }
+                    }
+                }
+            };
+            if loaded {
+                self.progress.loaded += 1;
+            } else {
+                self.progress.failed += 1;
+            }
+        }
+    }
+
+    /// progress of the background loads, for a loading screen
+    pub fn get_progress(&self) -> LoadProgress {
+        self.progress
+    }
+
+    ///
+    /// stand in model for entities whose assets are still loading. it is
+    /// never drawn (the MasterRenderer skips entities that are not ready),
+    /// it only gives the entity something to hold
+    ///
+    pub fn get_placeholder(&mut self) -> TexturedModel {
+        if let Some(placeholder) = &self.placeholder {
+            return placeholder.clone();
+        }
+        let model = self.loader.triangle();
+        let texture = self
+            .loader
+            .upload_texture(
+                &TextureData::solid_colour(glam::Vec4::ONE),
+                &TextureOptions::default(),
+            )
+            .expect("a 1x1 texture always fits");
+        let placeholder = TexturedModel::new(&model, &texture);
+        self.placeholder = Some(placeholder.clone());
+        placeholder
+    }
+
+    ///
+    /// reloads every asset loaded from `path`. on an error nothing is replaced,
+    /// so the old version keeps being drawn. paths nothing was loaded from, or
+    /// that are still loading, are ignored
+    ///
+    pub fn reload(&mut self, path: &Path) -> Result<Vec<Reloaded>, ReloadError> {
+        let key = AssetManager::normalise_path(&path.to_string_lossy());
+        let mut reloaded = Vec::new();
+
+        if let Some(handle) = self
+            .models
+            .handles
+            .get(&key)
+            .filter(|handle| handle.is_ready())
+            .cloned()
+        {
+            // the same path as the first load, so the levels of detail come back too
+            let model = self
+                .loader
+                .load_3d_model_cached(&key.to_string_lossy())
+                .map_err(ReloadError::Model)?;
+            let new = Arc::new(model.clone());
+            if let Some(old) = handle.replace(model) {
+                reloaded.push(Reloaded::Model { old, new });
+            }
+        }
+
+        let textures: Vec<(TextureOptions, Handle<ModelTexture>)> = self
+            .textures
+            .handles
+            .iter()
+            .filter(|((texture_path, _), _)| *texture_path == key)
+            .map(|((_, options), handle)| (options.clone(), handle.clone()))
+            .collect();
+        for (options, handle) in textures {
+            let Some(current) = handle.get() else {
+                continue;
+            };
+            let fresh = self
+                .loader
+                .load_texture_from_path(&key.to_string_lossy(), &options)
+                .map_err(ReloadError::Texture)?;
+            // keep the id and settings so batches and entities still match
+            let mut texture = (*current).clone();
+            texture.diffuse_texture_view = fresh.diffuse_texture_view;
+            texture.diffuse_bind_group = fresh.diffuse_bind_group;
+            let new = Arc::new(texture.clone());
+            if let Some(old) = handle.replace(texture) {
+                reloaded.push(Reloaded::Texture { old, new });
+            }
+        }
+
+        Ok(reloaded)
+    }
+
+    /// forgets assets that no handle, entity or other copy uses any more,
+    /// update does this every frame
+    pub fn remove_unused(&mut self) {
+        self.models.remove_unused();
+        self.textures.remove_unused();
+    }
+
+    /// number of models and textures that are still in use
+    pub fn loaded_count(&self) -> (usize, usize) {
+        (self.models.in_use_count(), self.textures.in_use_count())
+    }
+}
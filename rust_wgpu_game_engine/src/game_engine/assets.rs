@@ -0,0 +1,71 @@
+//!
+//! assets - typed constants for everything in res/, generated by build.rs
+//!
+//! use models::CUBE rather than "res/cube.obj", a renamed or deleted file is
+//! then a compile error instead of a load error at runtime. every asset has
+//! been checked by the build, so loading one can only fail if the file was
+//! changed afterwards
+//!
+//! paths are relative to asset_root, so they work wherever the game is run from
+//! and on machines other than the one that built it
+//!
+
+use std::path::{Path, PathBuf};
+
+///
+/// the folder asset paths are relative to - ASSET_ROOT if it is set, otherwise
+/// the crate folder in debug builds (so hot reload sees edits to the source
+/// files) and the folder of the executable in release builds, where res/ is
+/// shipped next to it
+///
+pub fn asset_root() -> PathBuf {
+    if let Some(root) = std::env::var_os("ASSET_ROOT") {
+        return PathBuf::from(root);
+    }
+    if cfg!(debug_assertions) {
+        return PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    }
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+/// a path relative to asset_root as one the loaders can open
+pub fn resolve(path: &str) -> String {
+    asset_root().join(path).to_string_lossy().into_owned()
+}
+
+/// an obj file and the binary mesh the build converted it into
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ModelAsset {
+    pub id: u32,
+    /// the source file, relative to asset_root. hot reload watches it
+    pub path: &'static str,
+    /// the .mesh the build converted it into, embedded in the binary.
+    /// see Loader::load_3d_model_from_bytes
+    pub mesh: &'static [u8],
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureAsset {
+    pub id: u32,
+    /// relative to asset_root
+    pub path: &'static str,
+}
+
+impl ModelAsset {
+    /// where the source file is on this machine
+    pub fn full_path(&self) -> String {
+        resolve(self.path)
+    }
+}
+
+impl TextureAsset {
+    /// where the image is on this machine
+    pub fn full_path(&self) -> String {
+        resolve(self.path)
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/asset_manifest.rs"));
@@ -0,0 +1,147 @@
+///
+/// BackgroundLoader - a small pool of worker threads for asset decoding
+///
+/// the workers do everything that does not need the gpu: reading files,
+/// parsing obj (or reading its binary cache), decoding images, building mip
+/// chains and interleaving vertices. finished work is collected on the main
+/// thread, which does the upload (see AssetManager::update)
+///
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::JoinHandle,
+};
+
+use crate::game_engine::{
+    mesh_cache::{CachedMesh, MeshCacheError},
+    texture_data::{TextureData, TextureError, TextureOptions},
+};
+
+pub enum LoadJob {
+    Model(PathBuf),
+    Texture(PathBuf, TextureOptions),
+}
+
+/// the cpu side of an asset, ready to upload
+pub enum Decoded {
+    Model {
+        path: PathBuf,
+        result: Result<CachedMesh, MeshCacheError>,
+    },
+    Texture {
+        path: PathBuf,
+        options: TextureOptions,
+        /// the full mip chain when the options ask for one, otherwise one level
+        result: Result<Vec<TextureData>, TextureError>,
+    },
+}
+
+impl LoadJob {
+    fn run(self) -> Decoded {
+        match self {
+            LoadJob::Model(path) => {
+                let result = CachedMesh::load_or_convert(&path.to_string_lossy());
+                Decoded::Model { path, result }
+            }
+            LoadJob::Texture(path, options) => {
+                let result = TextureData::from_path(&path.to_string_lossy()).map(|data| {
+                    if options.generate_mipmaps {
+                        data.generate_mipmaps(options.srgb)
+                    } else {
+                        vec![data]
+                    }
+                });
+                Decoded::Texture {
+                    path,
+                    options,
+                    result,
+                }
+            }
+        }
+    }
+}
+
+pub struct BackgroundLoader {
+    jobs: Option<Sender<LoadJob>>,
+    results: Receiver<Decoded>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl BackgroundLoader {
+    /// starts one worker per core, leaving one for the render thread
+    pub fn new() -> Self {
+        let count = std::thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1);
+        BackgroundLoader::with_workers(count)
+    }
+
+    pub fn with_workers(count: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<LoadJob>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..count.max(1))
+            .map(|i| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                std::thread::Builder::new()
+                    .name(format!("asset loader {}", i))
+                    .spawn(move || {
+                        loop {
+                            // only hold the lock while taking a job, not while running it
+                            let job = match job_receiver.lock() {
+                                Ok(receiver) => receiver.recv(),
+                                Err(_) => return,
+                            };
+                            let Ok(job) = job else {
+                                return; // the loader was dropped
+                            };
+                            if result_sender.send(job.run()).is_err() {
+                                return;
+                            }
+                        }
+                    })
+                    .expect("can not start asset loader thread")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+        }
+    }
+
+    pub fn queue(&self, job: LoadJob) {
+        if let Some(jobs) = &self.jobs {
+            // only fails if every worker has died, the handle then just stays pending
+            let _ = jobs.send(job);
+        }
+    }
+
+    /// everything the workers have finished since the last call, never blocks
+    pub fn finished(&self) -> Vec<Decoded> {
+        self.results.try_iter().collect()
+    }
+}
+
+impl Default for BackgroundLoader {
+    fn default() -> Self {
+        BackgroundLoader::new()
+    }
+}
+
+impl Drop for BackgroundLoader {
+    fn drop(&mut self) {
+        // closing the channel lets the workers finish their current job and stop
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
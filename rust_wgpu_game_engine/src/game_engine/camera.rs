@@ -1,10 +1,20 @@
 use glam::{EulerRot, Mat4, Quat, Vec3};
 
+use super::projection::Projection;
+
 pub struct Camera {
     pub position: Vec3,
     pub pitch: f32, // up or down
     pub yaw: f32,   // left or right
     pub roll: f32,  // spin
+    /// the lens, kept in step with the window by State::resize
+    pub projection: Projection,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
@@ -14,6 +24,7 @@ impl Camera {
             pitch: 0.0,
             yaw: 0.0,
             roll: 0.0,
+            projection: Projection::default(),
         }
     }
 
@@ -23,6 +34,10 @@ impl Camera {
     Mat4::from_rotation_translation(rotation, self.position).inverse()
 }
 
+    pub fn build_projection_matrix(&self) -> Mat4 {
+        self.projection.matrix()
+    }
+
     pub fn get_position(&self) -> Vec3 {
         self.position
     }
@@ -0,0 +1,92 @@
+///
+/// depth texture - the depth buffer of the main render pass
+///
+/// every pipeline drawing into the pass has to be built with the same
+/// DepthSettings, how depth is stored and tested, as the texture
+///
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DepthSettings {
+    pub format: wgpu::TextureFormat,
+    /// a fragment is drawn when `compare(fragment depth, stored depth)` passes
+    pub compare: wgpu::CompareFunction,
+}
+
+impl Default for DepthSettings {
+    fn default() -> Self {
+        Self {
+            format: wgpu::TextureFormat::Depth32Float,
+            compare: wgpu::CompareFunction::Less,
+        }
+    }
+}
+
+impl DepthSettings {
+    /// the depth state pipelines drawing into the pass are built with
+    pub fn depth_stencil_state(&self) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: self.format,
+            depth_write_enabled: true,
+            depth_compare: self.compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+
+    /// the depth nothing has been drawn at, the far plane for `compare`
+    pub fn clear_value(&self) -> f32 {
+        match self.compare {
+            wgpu::CompareFunction::Greater | wgpu::CompareFunction::GreaterEqual => 0.0,
+            _ => 1.0,
+        }
+    }
+}
+
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub settings: DepthSettings,
+}
+
+impl DepthTexture {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, settings: DepthSettings) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: settings.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            settings,
+        }
+    }
+
+    /// the depth buffer has to match the size of the surface it is drawn with
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        *self = DepthTexture::new(device, width, height, self.settings);
+    }
+
+    /// cleared at the start of the pass, not kept after it
+    pub fn attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(self.settings.clear_value()),
+                store: wgpu::StoreOp::Discard,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
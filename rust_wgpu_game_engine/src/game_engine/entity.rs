@@ -1,5 +1,10 @@
-use super::textured_model::TexturedModel;
-use glam::{Mat4, Quat, Vec2, Vec3};
+use super::{
+    asset_manager::PendingModel,
+    bounds::{Aabb, BoundingSphere},
+    texture_atlas::AtlasRegion,
+    textured_model::TexturedModel,
+};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 
 #[derive(Clone)] // Added Clone derivation
 pub struct Entity {
@@ -10,6 +15,14 @@ pub struct Entity {
     pub rot_z: f32,
     pub scale: f32,
     pub texture_index: u32, // Corrected typo from 'texure'
+    /// named sub-rect of a packed atlas, used instead of texture_index when set
+    pub texture_region: Option<AtlasRegion>,
+    /// assets still loading in the background, `model` is only a placeholder until they are ready
+    pub pending: Option<PendingModel>,
+    /// level of detail drawn, kept between frames by MasterRenderer::update_lods
+    pub lod_level: usize,
+    /// multiplied with the texture colour, white leaves it as it is
+    pub tint: Vec4,
 }
 
 impl Entity {
@@ -30,13 +43,67 @@ impl Entity {
             rot_z,
             scale,
             texture_index,
+            texture_region: None,
+            pending: None,
+            lod_level: 0,
+            tint: Vec4::ONE,
         }
     }
 
+    /// builds an entity from a transformation matrix.
+    /// entities only have a uniform scale, so the average of the axis scales
+    /// is used, with a warning when the axes are scaled differently
+    pub fn from_transform(model: TexturedModel, transform: Mat4, texture_index: u32) -> Self {
+        let (scale, rotation, position) = transform.to_scale_rotation_translation();
+        if scale.max_element() - scale.min_element() > 1e-3 * scale.abs().max_element() {
+            log::warn!(
+                "non uniform scale {} can not be kept, every axis is scaled by {}",
+                scale,
+                scale.element_sum() / 3.0
+            );
+        }
+        let (rot_x, rot_y, rot_z) = rotation.to_euler(glam::EulerRot::XYZ);
+        Self::new(
+            model,
+            position,
+            rot_x,
+            rot_y,
+            rot_z,
+            scale.element_sum() / 3.0,
+            texture_index,
+        )
+    }
+
     pub fn set_texture_index(&mut self, new_index: u32) {
         self.texture_index = new_index;
     }
 
+    pub fn set_pending(&mut self, pending: Option<PendingModel>) {
+        self.pending = pending;
+    }
+
+    /// false while the entity is still waiting on background loads
+    pub fn is_ready(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// swaps in the real model once its pending assets are ready, returns is_ready
+    pub fn update_pending(&mut self) -> bool {
+        if let Some(model) = self.pending.as_ref().and_then(|pending| pending.get()) {
+            self.model = model;
+            self.pending = None;
+        }
+        self.is_ready()
+    }
+
+    pub fn set_tint(&mut self, tint: Vec4) {
+        self.tint = tint;
+    }
+
+    pub fn set_texture_region(&mut self, region: Option<AtlasRegion>) {
+        self.texture_region = region;
+    }
+
     pub fn increase_position(&mut self, dx: f32, dy: f32, dz: f32) {
         self.position += Vec3::new(dx, dy, dz);
     }
@@ -58,6 +125,9 @@ impl Entity {
 
     // Simplified texture offset logic
     pub fn get_texture_offset(&self) -> Vec2 {
+        if let Some(region) = self.texture_region {
+            return region.offset;
+        }
         let rows = self.model.get_texture().number_of_rows as f32;
         let column = (self.texture_index as f32 % rows) / rows;
         let row = (self.texture_index as f32 / rows).floor() / rows;
@@ -65,6 +135,14 @@ impl Entity {
         Vec2::new(column, row)
     }
 
+    /// how much of the texture one copy of the model covers in uv space
+    pub fn get_texture_scale(&self) -> Vec2 {
+        match self.texture_region {
+            Some(region) => region.size,
+            None => Vec2::splat(1.0 / self.model.get_texture().number_of_rows as f32),
+        }
+    }
+
     pub fn create_transformation_matrix(&self) -> Mat4 {
         Mat4::from_scale_rotation_translation(
             Vec3::splat(self.scale),
@@ -72,4 +150,20 @@ impl Entity {
             self.position,
         )
     }
+
+    /// the model's bounding box in world space, grown to stay axis aligned when rotated
+    pub fn get_world_aabb(&self) -> Aabb {
+        self.model
+            .model
+            .aabb
+            .transform(&self.create_transformation_matrix())
+    }
+
+    /// the model's bounding sphere in world space
+    pub fn get_world_bounding_sphere(&self) -> BoundingSphere {
+        self.model
+            .model
+            .bounding_sphere
+            .transform(&self.create_transformation_matrix())
+    }
 }
@@ -0,0 +1,554 @@
+///
+/// glTF loader - reads .gltf (with external or embedded buffers) and binary .glb files
+///
+/// GltfScene holds everything decoded on the cpu, so it can be inspected without a gpu.
+/// Loader::load_gltf / load_gltf_from_slice upload a scene as RawModels, ModelTextures
+/// and Entities
+///
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::game_engine::{
+    entity::Entity, loader::Loader, mesh_processing::NormalMode, model_texture::ModelTexture,
+    obj_parser::ObjData, raw_model::RawModel, texture_data::SamplerDesc, texture_data::TextureData,
+    texture_data::TextureError, texture_data::TextureOptions, textured_model::TexturedModel,
+};
+
+#[derive(Debug)]
+pub enum GltfError {
+    /// a file (the model, or a buffer / image it references) could not be read
+    Io(PathBuf, std::io::Error),
+    /// the document itself is not valid gltf / glb
+    Gltf(gltf::Error),
+    /// a buffer or image uri could not be resolved
+    Uri(String),
+    /// an embedded or referenced image could not be decoded
+    Texture(TextureError),
+    /// a primitive is missing an attribute we can not do without
+    MissingAttribute(&'static str),
+    /// a buffer, or a view into one, ends before the data the file says is there
+    Truncated(String),
+    /// an attribute of a primitive has a different number of values than POSITION
+    AttributeCount {
+        name: &'static str,
+        count: usize,
+        expected: usize,
+    },
+    /// an index of a primitive points past its vertices
+    IndexOutOfRange { index: u32, vertex_count: usize },
+    /// a node has more than one parent or is its own ancestor, so the
+    /// nodes do not form a tree
+    NodeHierarchy(usize),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            GltfError::Gltf(e) => write!(f, "invalid gltf ({})", e),
+            GltfError::Uri(uri) => write!(f, "can not resolve uri '{}'", uri),
+            GltfError::Texture(e) => write!(f, "{}", e),
+            GltfError::MissingAttribute(name) => write!(f, "primitive has no {}", name),
+            GltfError::Truncated(what) => write!(f, "{} is cut short", what),
+            GltfError::AttributeCount {
+                name,
+                count,
+                expected,
+            } => write!(
+                f,
+                "primitive has {} {} values for {} positions",
+                count, name, expected
+            ),
+            GltfError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "primitive index {} is past its {} vertices",
+                index, vertex_count
+            ),
+            GltfError::NodeHierarchy(node) => {
+                write!(f, "node {} has two parents or is its own ancestor", node)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Io(_, e) => Some(e),
+            GltfError::Gltf(e) => Some(e),
+            GltfError::Texture(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(e: gltf::Error) -> Self {
+        GltfError::Gltf(e)
+    }
+}
+
+impl From<TextureError> for GltfError {
+    fn from(e: TextureError) -> Self {
+        GltfError::Texture(e)
+    }
+}
+
+/// one drawable part of a mesh, indices into GltfScene::materials
+pub struct GltfPrimitive {
+    pub data: ObjData,
+    pub material: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// material properties mapped onto what ModelTexture understands.
+/// textures are indices into GltfScene::images
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_colour: Vec4,
+    pub base_colour_texture: Option<usize>,
+    pub base_colour_sampler: SamplerDesc,
+    pub normal_texture: Option<usize>,
+    pub normal_sampler: SamplerDesc,
+    pub shine_damper: f32,
+    pub reflectivity: f32,
+    pub has_transparency: bool,
+}
+
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub mesh: Option<usize>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub local_transform: Mat4,
+    pub world_transform: Mat4,
+}
+
+/// cpu side contents of a gltf file
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<TextureData>,
+    pub nodes: Vec<GltfNode>,
+}
+
+/// a gltf scene once it is on the gpu
+pub struct GltfModel {
+    /// one TexturedModel per primitive, for each mesh
+    pub meshes: Vec<Vec<TexturedModel>>,
+    /// the node hierarchy, same indices as the file
+    pub nodes: Vec<GltfNode>,
+    /// an entity for every primitive of every node with a mesh, placed at the node's world transform
+    pub entities: Vec<Entity>,
+}
+
+impl GltfScene {
+    /// loads a .gltf or .glb file, external buffers and images are relative to it
+    pub fn from_path(path: &str) -> Result<GltfScene, GltfError> {
+        let bytes = std::fs::read(path).map_err(|e| GltfError::Io(PathBuf::from(path), e))?;
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        GltfScene::from_slice_with_directory(&bytes, Some(directory))
+    }
+
+    /// loads .gltf or .glb data already in memory. only embedded (glb or data uri)
+    /// buffers and images can be used
+    pub fn from_slice(bytes: &[u8]) -> Result<GltfScene, GltfError> {
+        GltfScene::from_slice_with_directory(bytes, None)
+    }
+
+    fn from_slice_with_directory(
+        bytes: &[u8],
+        directory: Option<&Path>,
+    ) -> Result<GltfScene, GltfError> {
+        let gltf = gltf::Gltf::from_slice(bytes)?;
+
+        let mut buffers = Vec::new();
+        for buffer in gltf.buffers() {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => gltf
+                    .blob
+                    .clone()
+                    .ok_or_else(|| GltfError::Uri("glb binary chunk".to_string()))?,
+                gltf::buffer::Source::Uri(uri) => GltfScene::read_uri(uri, directory)?,
+            };
+            // a truncated glb or a short .bin, the accessors would read past the end
+            if data.len() < buffer.length() {
+                return Err(GltfError::Truncated(format!("buffer {}", buffer.index())));
+            }
+            buffers.push(data);
+        }
+
+        let mut images = Vec::new();
+        for image in gltf.images() {
+            let encoded = match image.source() {
+                gltf::image::Source::View { view, .. } => buffers[view.buffer().index()]
+                    .get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| GltfError::Truncated(format!("buffer view {}", view.index())))?
+                    .to_vec(),
+                gltf::image::Source::Uri { uri, .. } => GltfScene::read_uri(uri, directory)?,
+            };
+            images.push(TextureData::from_bytes(&encoded)?);
+        }
+
+        let materials = gltf.materials().map(GltfScene::read_material).collect();
+
+        let mut meshes = Vec::new();
+        for mesh in gltf.meshes() {
+            let mut primitives = Vec::new();
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    log::warn!(
+                        "skipping {:?} primitive in mesh {}",
+                        primitive.mode(),
+                        mesh.index()
+                    );
+                    continue;
+                }
+                primitives.push(GltfPrimitive {
+                    data: GltfScene::read_primitive(&primitive, &buffers)?,
+                    material: primitive.material().index(),
+                });
+            }
+            meshes.push(GltfMesh {
+                name: mesh.name().map(|n| n.to_string()),
+                primitives,
+            });
+        }
+
+        let nodes = GltfScene::read_nodes(&gltf)?;
+
+        Ok(GltfScene {
+            meshes,
+            materials,
+            images,
+            nodes,
+        })
+    }
+
+    /// resolves a data: uri or a path relative to the model file
+    fn read_uri(uri: &str, directory: Option<&Path>) -> Result<Vec<u8>, GltfError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (_, encoded) = data
+                .split_once(";base64,")
+                .ok_or_else(|| GltfError::Uri(uri.to_string()))?;
+            return base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| GltfError::Uri(uri.to_string()));
+        }
+        let directory = directory.ok_or_else(|| GltfError::Uri(uri.to_string()))?;
+        let path = directory.join(GltfScene::percent_decode(uri));
+        std::fs::read(&path).map_err(|e| GltfError::Io(path, e))
+    }
+
+    /// uris are allowed to escape characters, eg spaces as %20
+    fn percent_decode(uri: &str) -> String {
+        let bytes = uri.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escaped = (bytes[i] == b'%')
+                .then(|| uri.get(i + 1..i + 3))
+                .flatten()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    fn read_material(material: gltf::Material) -> GltfMaterial {
+        let pbr = material.pbr_metallic_roughness();
+        // blinn-phong exponent that roughly matches a ggx roughness
+        let alpha = pbr.roughness_factor().max(0.01).powi(2);
+        let shine_damper = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 256.0);
+        // metals reflect everything, dielectrics about 4%, both fade out with roughness
+        let reflectivity = (0.04 + 0.96 * pbr.metallic_factor()) * (1.0 - pbr.roughness_factor());
+
+        GltfMaterial {
+            name: material.name().map(|n| n.to_string()),
+            base_colour: Vec4::from_array(pbr.base_color_factor()),
+            base_colour_texture: pbr
+                .base_color_texture()
+                .map(|info| info.texture().source().index()),
+            base_colour_sampler: pbr
+                .base_color_texture()
+                .map(|info| GltfScene::read_sampler(info.texture().sampler()))
+                .unwrap_or_default(),
+            normal_texture: material
+                .normal_texture()
+                .map(|info| info.texture().source().index()),
+            normal_sampler: material
+                .normal_texture()
+                .map(|info| GltfScene::read_sampler(info.texture().sampler()))
+                .unwrap_or_default(),
+            shine_damper,
+            reflectivity,
+            has_transparency: material.alpha_mode() != gltf::material::AlphaMode::Opaque,
+        }
+    }
+
+    /// maps a gltf sampler onto a SamplerDesc, gltf defaults to repeating
+    fn read_sampler(sampler: gltf::texture::Sampler) -> SamplerDesc {
+        use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+        let address_mode = |mode: WrappingMode| match mode {
+            WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+            WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+        };
+        let mut desc = SamplerDesc {
+            address_mode_u: address_mode(sampler.wrap_s()),
+            address_mode_v: address_mode(sampler.wrap_t()),
+            ..Default::default()
+        };
+        if let Some(MagFilter::Nearest) = sampler.mag_filter() {
+            desc.mag_filter = wgpu::FilterMode::Nearest;
+        }
+        if let Some(min_filter) = sampler.min_filter() {
+            (desc.min_filter, desc.mipmap_filter) = match min_filter {
+                MinFilter::Nearest | MinFilter::NearestMipmapNearest => {
+                    (wgpu::FilterMode::Nearest, wgpu::MipmapFilterMode::Nearest)
+                }
+                MinFilter::Linear | MinFilter::LinearMipmapNearest => {
+                    (wgpu::FilterMode::Linear, wgpu::MipmapFilterMode::Nearest)
+                }
+                MinFilter::NearestMipmapLinear => {
+                    (wgpu::FilterMode::Nearest, wgpu::MipmapFilterMode::Linear)
+                }
+                MinFilter::LinearMipmapLinear => {
+                    (wgpu::FilterMode::Linear, wgpu::MipmapFilterMode::Linear)
+                }
+            };
+        }
+        desc
+    }
+
+    ///
+    /// reads a triangle primitive into ObjData.
+    /// missing texture coordinates become (0, 0). without normals the spec
+    /// asks for flat shading, and any tangents are then ignored.
+    /// every attribute has to have a value for each position and every index
+    /// has to point at one, so nothing later reads past the end
+    ///
+    fn read_primitive(
+        primitive: &gltf::Primitive,
+        buffers: &[Vec<u8>],
+    ) -> Result<ObjData, GltfError> {
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| b.as_slice()));
+
+        let positions: Vec<Vec3> = reader
+            .read_positions()
+            .ok_or(GltfError::MissingAttribute("POSITION"))?
+            .map(Vec3::from_array)
+            .collect();
+        let check_count = |name: &'static str, count: usize| {
+            if count == positions.len() {
+                Ok(())
+            } else {
+                Err(GltfError::AttributeCount {
+                    name,
+                    count,
+                    expected: positions.len(),
+                })
+            }
+        };
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+            return Err(GltfError::IndexOutOfRange {
+                index,
+                vertex_count: positions.len(),
+            });
+        }
+        let tex_coords: Vec<Vec2> = match reader.read_tex_coords(0) {
+            Some(tex_coords) => tex_coords.into_f32().map(Vec2::from_array).collect(),
+            None => vec![Vec2::ZERO; positions.len()],
+        };
+        check_count("TEXCOORD_0", tex_coords.len())?;
+        let data = ObjData {
+            vertices: positions.iter().flat_map(|p| p.to_array()).collect(),
+            texture: tex_coords.iter().flat_map(|t| t.to_array()).collect(),
+            normal: Vec::new(),
+            indice: indices,
+            tangent: Vec::new(),
+        };
+
+        match reader.read_normals() {
+            Some(normals) => {
+                let normal: Vec<[f32; 3]> = normals.collect();
+                check_count("NORMAL", normal.len())?;
+                let tangent: Vec<[f32; 4]> = reader
+                    .read_tangents()
+                    .map(|tangents| tangents.collect())
+                    .unwrap_or_default();
+                if !tangent.is_empty() {
+                    check_count("TANGENT", tangent.len())?;
+                }
+                Ok(ObjData {
+                    normal: normal.into_iter().flatten().collect(),
+                    tangent: tangent.into_iter().flatten().collect(),
+                    ..data
+                })
+            }
+            None => Ok(data.generate_normals(NormalMode::Flat)),
+        }
+    }
+
+    ///
+    /// reads every node and works out its world transform from its parents.
+    /// gltf only checks child indices are in range, a node listed as a child
+    /// twice or a cycle would otherwise walk forever
+    ///
+    fn read_nodes(gltf: &gltf::Gltf) -> Result<Vec<GltfNode>, GltfError> {
+        let mut nodes: Vec<GltfNode> = gltf
+            .nodes()
+            .map(|node| GltfNode {
+                name: node.name().map(|n| n.to_string()),
+                mesh: node.mesh().map(|m| m.index()),
+                parent: None,
+                children: node.children().map(|c| c.index()).collect(),
+                local_transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+                world_transform: Mat4::IDENTITY,
+            })
+            .collect();
+
+        for index in 0..nodes.len() {
+            for child in nodes[index].children.clone() {
+                if nodes[child].parent.is_some() {
+                    return Err(GltfError::NodeHierarchy(child));
+                }
+                nodes[child].parent = Some(index);
+            }
+        }
+
+        // walk down from every root so parents are always done before children
+        let mut stack: Vec<(usize, Mat4)> = (0..nodes.len())
+            .filter(|&i| nodes[i].parent.is_none())
+            .map(|i| (i, Mat4::IDENTITY))
+            .collect();
+        let mut visited = vec![false; nodes.len()];
+        while let Some((index, parent_transform)) = stack.pop() {
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(GltfError::NodeHierarchy(index));
+            }
+            let node = &mut nodes[index];
+            node.world_transform = parent_transform * node.local_transform;
+            let world_transform = node.world_transform;
+            stack.extend(node.children.iter().map(|&c| (c, world_transform)));
+        }
+        // a cycle with no root above it is never reached from the roots
+        if let Some(index) = visited.iter().position(|&v| !v) {
+            return Err(GltfError::NodeHierarchy(index));
+        }
+
+        Ok(nodes)
+    }
+}
+
+impl Loader {
+    /// loads a .gltf or .glb file and uploads it
+    pub fn load_gltf(&mut self, path: &str) -> Result<GltfModel, GltfError> {
+        let scene = GltfScene::from_path(path)?;
+        self.upload_gltf(scene)
+    }
+
+    /// loads .gltf or .glb data already in memory and uploads it
+    pub fn load_gltf_from_slice(&mut self, bytes: &[u8]) -> Result<GltfModel, GltfError> {
+        let scene = GltfScene::from_slice(bytes)?;
+        self.upload_gltf(scene)
+    }
+
+    ///
+    /// uploads a decoded scene. materials without a base colour texture get a
+    /// single pixel texture of their base colour, primitives without a material
+    /// get the default texture
+    ///
+    pub fn upload_gltf(&mut self, scene: GltfScene) -> Result<GltfModel, GltfError> {
+        let mut textures: Vec<ModelTexture> = Vec::with_capacity(scene.materials.len());
+        for material in &scene.materials {
+            let colour_options = TextureOptions {
+                sampler: material.base_colour_sampler,
+                ..Default::default()
+            };
+            let mut texture = match material.base_colour_texture {
+                Some(image) => self.upload_texture(&scene.images[image], &colour_options)?,
+                None => self.upload_texture(
+                    &TextureData::solid_colour(material.base_colour),
+                    &colour_options,
+                )?,
+            };
+            if let Some(image) = material.normal_texture {
+                let normal_options = TextureOptions {
+                    sampler: material.normal_sampler,
+                    ..TextureOptions::linear()
+                };
+                let normal_map = self.upload_texture(&scene.images[image], &normal_options)?;
+                texture.normal_map = Some(normal_map.diffuse_bind_group);
+            }
+            texture.shine_damper = material.shine_damper;
+            texture.relfectivity = material.reflectivity;
+            texture.has_transparency = material.has_transparency;
+            textures.push(texture);
+        }
+        let mut default_texture: Option<ModelTexture> = None;
+
+        let mut meshes = Vec::with_capacity(scene.meshes.len());
+        for mesh in &scene.meshes {
+            let mut models = Vec::with_capacity(mesh.primitives.len());
+            for primitive in &mesh.primitives {
+                let raw_model: RawModel =
+                    self.upload_mesh(&primitive.data.to_vertices(), &primitive.data.indice);
+                let texture = match primitive.material {
+                    Some(material) => textures[material].clone(),
+                    None => default_texture
+                        .get_or_insert_with(|| self.load_texture())
+                        .clone(),
+                };
+                models.push(TexturedModel::new(&raw_model, &texture));
+            }
+            meshes.push(models);
+        }
+
+        let mut entities = Vec::new();
+        for node in &scene.nodes {
+            if let Some(mesh) = node.mesh {
+                for model in &meshes[mesh] {
+                    entities.push(Entity::from_transform(
+                        model.clone(),
+                        node.world_transform,
+                        0,
+                    ));
+                }
+            }
+        }
+
+        Ok(GltfModel {
+            meshes,
+            nodes: scene.nodes,
+            entities,
+        })
+    }
+}
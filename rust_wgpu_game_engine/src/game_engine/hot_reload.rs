@@ -0,0 +1,84 @@
+///
+/// HotReloader - watches asset files while the game runs (dev mode)
+///
+/// the watcher thread only records which files changed, the main thread asks
+/// for them once a frame with poll_changes and does the reloading itself.
+/// editors tend to write a file in several steps (truncate, write, rename), so
+/// a path is only reported once it has been quiet for a short while
+///
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// how long a file has to stay untouched before it is reloaded
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+pub struct HotReloader {
+    watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// changed files and when they were last touched
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl HotReloader {
+    pub fn new() -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            // the receiver is gone once the reloader is dropped, nothing to do then
+            let _ = sender.send(event);
+        })?;
+        Ok(Self {
+            watcher,
+            events,
+            pending: HashMap::new(),
+        })
+    }
+
+    ///
+    /// starts watching a folder (and everything in it) or a single file.
+    /// for a file its folder is watched, so saving through a rename still
+    /// gets noticed
+    ///
+    pub fn watch(&mut self, path: &str) -> notify::Result<()> {
+        let path = std::fs::canonicalize(path).map_err(notify::Error::io)?;
+        if path.is_dir() {
+            self.watcher.watch(&path, RecursiveMode::Recursive)
+        } else {
+            let folder = path.parent().unwrap_or(Path::new("/"));
+            self.watcher.watch(folder, RecursiveMode::NonRecursive)
+        }
+    }
+
+    /// files that changed and have settled since the last call, as absolute paths
+    pub fn poll_changes(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in event.paths {
+                            self.pending.insert(path, now);
+                        }
+                    }
+                }
+                Err(e) => log::warn!("file watcher: {}", e),
+            }
+        }
+
+        let mut settled = Vec::new();
+        self.pending.retain(|path, touched| {
+            if now.duration_since(*touched) < SETTLE_TIME {
+                return true;
+            }
+            // canonical paths match the keys the AssetManager uses
+            settled.push(std::fs::canonicalize(path.as_path()).unwrap_or_else(|_| path.clone()));
+            false
+        });
+        settled
+    }
+}
@@ -1,17 +1,25 @@
 use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
 
+use super::model_texture::ModelTexture;
+
+/// same layout as `Light` in shader.wgsl, checked in tests/uniform_layout.rs
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
 pub struct Light {
     pub position: Vec3,
-    // Note: Some wgpu setups require 4-byte padding after a Vec3
-    // to satisfy WGSL's 16-byte alignment rules for uniforms.
+    // a vec3 is 16-byte aligned in WGSL, so each one is followed by 4 bytes of padding
     pub _padding: f32,
     pub colour: Vec3,
     pub _padding2: f32,
 }
 
+impl Default for Light {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Light {
     pub fn new() -> Self {
         Self {
@@ -32,3 +40,39 @@ impl Light {
         self.colour
     }
 }
+
+/// group 2 of the main shader, the same layout as `Lighting` in shader.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct LightingUniforms {
+    pub sun: Light,
+    pub camera_position: Vec3,
+    pub shine_damper: f32,
+    pub reflectivity: f32,
+    /// the least light any surface gets, so the side facing away is not black
+    pub ambient: f32,
+    pub _padding: [f32; 2],
+}
+
+impl LightingUniforms {
+    /// lighting for this frame, with a plain material until with_material is called
+    pub fn new(sun: &Light, camera_position: Vec3, ambient: f32) -> Self {
+        Self {
+            sun: *sun,
+            camera_position,
+            shine_damper: 1.0,
+            reflectivity: 0.0,
+            ambient,
+            _padding: [0.0; 2],
+        }
+    }
+
+    /// the same lighting, with the shininess of a model's texture
+    pub fn with_material(&self, texture: &ModelTexture) -> Self {
+        Self {
+            shine_damper: texture.get_shine(),
+            reflectivity: texture.get_reflectivity(),
+            ..*self
+        }
+    }
+}
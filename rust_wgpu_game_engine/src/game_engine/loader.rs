@@ -1,124 +1,82 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+use std::collections::HashMap;
+
+use crate::game_engine::{
+    material::Material,
+    mesh_cache::{CachedMesh, MeshCacheError},
+    mesh_simplification::{Lod, LodSettings},
+    model_texture::ModelTexture,
+    obj_error::ObjError,
+    obj_parser::{self, ObjData, TangentVertex, Vertex, vertex_bounds},
+    raw_model::RawModel,
+    texture_data::{SamplerDesc, TextureData, TextureError, TextureOptions},
+    textured_model::TexturedModel,
 };
-
-use crate::game_engine::{model_texture::ModelTexture, raw_model::RawModel};
 ///
 /// Loader - loads models and textures
 ///
-use wgpu::util::DeviceExt;
-
-/// vertext data struct implementation
-/// this is used for loading in to arrays
-/// from .obj file so that they can be used to create a
-/// rawmodel
+/// obj files are parsed by obj_parser, the Loader puts the result on the gpu
 ///
-pub struct ObjData {
-    pub vertices: Vec<f32>,
-    pub normal: Vec<f32>,
-    pub texture: Vec<f32>,
-    pub indice: Vec<u32>,
-}
-
-///
-/// stringmap implementation
-/// simple key based lookup -
-
-pub struct StringMap {
-    internal_map: HashMap<String, usize>,
-}
-
-impl StringMap {
-    pub fn new() -> Self {
-        Self {
-            internal_map: HashMap::new(),
-        }
-    }
-
-    /// adds a string to the map and returns its index
-    /// if the string is not in the map, it adds it
-
-    pub fn add(&mut self, index: &String) -> usize {
-        let result = self.internal_map.get_key_value(index);
-        match result {
-            Some(t) => {
-                return *t.1;
-            }
-            None => {
-                let map_size = self.internal_map.len();
-                self.internal_map.insert(index.clone(), map_size.clone());
-                return map_size;
-            }
-        }
-    }
-
-    pub fn len(&self) -> usize {
-        self.internal_map.len()
-    }
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Vertex {
-    pub position: [f32; 3],
-    pub tex_coords: [f32; 2],
-    pub normal: [f32; 3],
-}
+use wgpu::util::DeviceExt;
 
+/// the built in pentagon, flat in x y and facing +z
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-0.0868241, 0.49240386, 0.0],
         tex_coords: [0.5, 0.0],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [-0.49513406, 0.06958647, 0.0],
         tex_coords: [0.0, 0.4],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [0.44147372, 0.2347359, 0.0],
         tex_coords: [1.0, 0.4],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [-0.49513406, 0.06958647, 0.0],
         tex_coords: [0.0, 0.4],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [-0.21918549, -0.44939706, 0.0],
         tex_coords: [0.2, 1.0],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [0.44147372, 0.2347359, 0.0],
         tex_coords: [1.0, 0.4],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [-0.21918549, -0.44939706, 0.0],
         tex_coords: [0.2, 1.0],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [0.35966998, -0.3473291, 0.0],
         tex_coords: [0.8, 1.0],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         position: [0.44147372, 0.2347359, 0.0],
         tex_coords: [1.0, 0.4],
-        normal: [1.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
 ];
 
 const INDICES: &[u16] = &[0, 1, 2, 3, 4, 5, 6, 7, 8];
 
-impl Vertex {
-    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+/// the wgpu buffer layout of a vertex type. a trait because the vertex
+/// types live in engine_mesh, which knows nothing about wgpu
+pub trait VertexLayout {
+    fn desc() -> wgpu::VertexBufferLayout<'static>;
+}
+
+impl VertexLayout for Vertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -146,10 +104,27 @@ impl Vertex {
     }
 }
 
+impl VertexLayout for TangentVertex {
+    /// same locations as Vertex::desc, with the tangent at location 3
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x2,
+            2 => Float32x3,
+            3 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TangentVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
 pub struct Loader {
     device: wgpu::Device,
     queue: wgpu::Queue,
-    texture_counter: u32,
+    samplers: HashMap<SamplerDesc, wgpu::Sampler>,
 }
 
 impl Loader {
@@ -157,7 +132,7 @@ impl Loader {
         Self {
             device: device.clone(),
             queue,
-            texture_counter: 0,
+            samplers: HashMap::new(),
         }
     }
 
@@ -178,64 +153,94 @@ impl Loader {
                 usage: wgpu::BufferUsages::INDEX,
             });
 
+        let (aabb, bounding_sphere) = vertex_bounds(VERTICES);
         RawModel {
+            id: RawModel::next_id(),
             vertex_buffer,
             index_buffer,
+            index_format: wgpu::IndexFormat::Uint16,
             num_vertices: VERTICES.len() as u32,
             num_indices: INDICES.len() as u32,
+            lods: vec![Lod {
+                first_index: 0,
+                index_count: INDICES.len() as u32,
+            }],
+            aabb,
+            bounding_sphere,
+            users: Default::default(),
         }
     }
 
+    pub fn get_device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// the default texture, the 8 row maze atlas. built in so it is always available
     pub fn load_texture(&mut self) -> ModelTexture {
         let diffuse_bytes = include_bytes!("../../res/maze.png");
-        let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-        let diffuse_rgba = diffuse_image.to_rgba8();
-        let dimensions = image::GenericImageView::dimensions(&diffuse_image);
+        self.load_texture_from_bytes(diffuse_bytes, &TextureOptions::atlas())
+            .expect("built in texture is valid")
+    }
 
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
+    /// reads, decodes and uploads an image file
+    pub fn load_texture_from_path(
+        &mut self,
+        path: &str,
+        options: &TextureOptions,
+    ) -> Result<ModelTexture, TextureError> {
+        let data = TextureData::from_path(path)?;
+        self.upload_texture(&data, options)
+    }
 
-        let diffuse_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("diffuse_texture"),
-            view_formats: &[],
-        });
+    /// decodes and uploads an image held in memory
+    pub fn load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        options: &TextureOptions,
+    ) -> Result<ModelTexture, TextureError> {
+        let data = TextureData::from_bytes(bytes)?;
+        self.upload_texture(&data, options)
+    }
 
-        self.queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &diffuse_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &diffuse_rgba,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            texture_size,
-        );
-
-        let diffuse_texture_view =
-            diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let diffuse_sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
-            ..Default::default()
-        });
+    ///
+    /// returns the sampler for a description, creating it the first time
+    /// it is asked for
+    ///
+    pub fn get_sampler(&mut self, desc: &SamplerDesc) -> wgpu::Sampler {
+        let device = &self.device;
+        self.samplers
+            .entry(*desc)
+            .or_insert_with(|| device.create_sampler(&desc.to_descriptor(device.features())))
+            .clone()
+    }
+
+    ///
+    /// uploads decoded texture data and wraps it up as a ModelTexture
+    ///
+    pub fn upload_texture(
+        &mut self,
+        data: &TextureData,
+        options: &TextureOptions,
+    ) -> Result<ModelTexture, TextureError> {
+        let levels = if options.generate_mipmaps {
+            data.generate_mipmaps(options.srgb)
+        } else {
+            vec![data.clone()]
+        };
+        self.upload_texture_levels(&levels, options)
+    }
+
+    ///
+    /// uploads a mip chain that has already been built, largest level first.
+    /// lets the mipmaps be generated away from the render thread
+    ///
+    pub fn upload_texture_levels(
+        &mut self,
+        levels: &[TextureData],
+        options: &TextureOptions,
+    ) -> Result<ModelTexture, TextureError> {
+        let diffuse_texture_view = self.create_texture_view(levels, options)?;
+        let diffuse_sampler = self.get_sampler(&options.sampler);
 
         let texture_bind_group_layout =
             self.device
@@ -276,182 +281,263 @@ impl Loader {
             label: Some("diffuse_bind_group"),
         });
 
-        self.texture_counter += 1;
-        ModelTexture::new(self.texture_counter, diffuse_bind_group)
+        Ok(ModelTexture::new(
+            ModelTexture::next_id(),
+            diffuse_texture_view,
+            diffuse_bind_group,
+        ))
     }
 
     ///
-    /// helper function to split strings into a vector by whitespace
-    ///
+    /// uploads a mip chain as a texture without wrapping it in a bind group,
+    /// for materials that bind several textures together. the first level is
+    /// the full size image, an empty chain is an error
     ///
+    pub fn create_texture_view(
+        &self,
+        levels: &[TextureData],
+        options: &TextureOptions,
+    ) -> Result<wgpu::TextureView, TextureError> {
+        let data = levels.first().ok_or(TextureError::NoLevels)?;
+        let max = self.device.limits().max_texture_dimension_2d;
+        if data.width > max || data.height > max {
+            return Err(TextureError::TooLarge {
+                width: data.width,
+                height: data.height,
+                max,
+            });
+        }
+
+        let texture_size = wgpu::Extent3d {
+            width: data.width,
+            height: data.height,
+            depth_or_array_layers: 1,
+        };
+
+        let diffuse_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            size: texture_size,
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: options.get_format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("diffuse_texture"),
+            view_formats: &[],
+        });
+
+        for (mip_level, level) in levels.iter().enumerate() {
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &diffuse_texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &level.pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * level.width),
+                    rows_per_image: Some(level.height),
+                },
+                wgpu::Extent3d {
+                    width: level.width,
+                    height: level.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
-    fn split_string_to_vec(input: &str) -> Vec<String> {
-        input.split_whitespace().map(|s| s.to_string()).collect()
+        Ok(diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default()))
     }
 
-    fn split_string_to_vec_using_slash(input: &str) -> Vec<String> {
-        input.split('/').map(|s| s.to_string()).collect()
+    ///
+    /// loads an object file and its materials as one TexturedModel per material.
+    /// textures come from map_Kd, materials without one get a single pixel
+    /// texture of their Kd colour and meshes without a material get the default texture
+    ///
+    pub fn load_textured_models(&mut self, filename: &str) -> Result<Vec<TexturedModel>, ObjError> {
+        let meshes = obj_parser::load_obj_meshes(filename)?;
+
+        let mut models = Vec::with_capacity(meshes.len());
+        for mesh in meshes {
+            let raw_model = self.upload_mesh(&mesh.data.to_vertices(), &mesh.data.indice);
+            let texture = match &mesh.material {
+                Some(material) => self.load_material_texture(material),
+                None => self.load_texture(),
+            };
+            models.push(TexturedModel::new(&raw_model, &texture));
+        }
+        Ok(models)
     }
 
     ///
-    /// Object loader reads in a wacefront object file and creates a raw model using the data
+    /// turns a material into a ModelTexture, falling back to the Kd colour
+    /// if its texture can not be read
     ///
+    fn load_material_texture(&mut self, material: &Material) -> ModelTexture {
+        let solid_colour =
+            TextureData::solid_colour(material.diffuse_colour.extend(material.dissolve));
+        let diffuse = match &material.diffuse_map {
+            Some(path) => match TextureData::from_path(&path.to_string_lossy()) {
+                Ok(data) => data,
+                Err(e) => {
+                    log::warn!("{}", e);
+                    solid_colour
+                }
+            },
+            None => solid_colour,
+        };
+        // obj texture coordinates are allowed to go outside 0..1 and tile
+        let colour_options = TextureOptions {
+            sampler: SamplerDesc::repeat(),
+            ..Default::default()
+        };
+        let mut texture = match self.upload_texture(&diffuse, &colour_options) {
+            Ok(texture) => texture,
+            Err(e) => {
+                log::warn!("{}: {}", material.name, e);
+                self.load_texture()
+            }
+        };
 
-    pub fn load_obj_file(filename: &str) -> ObjData {
-        let file = File::open(filename);
-        match file {
-            Err(_) => {
-                panic!("missing object file - can not continue: {}", filename);
+        if let Some(path) = &material.bump_map {
+            // normal maps hold directions, not colours, so they stay linear
+            let normal_options = TextureOptions {
+                sampler: SamplerDesc::repeat(),
+                ..TextureOptions::linear()
+            };
+            match self.load_texture_from_path(&path.to_string_lossy(), &normal_options) {
+                Ok(normal_map) => texture.normal_map = Some(normal_map.diffuse_bind_group),
+                Err(e) => log::warn!("{}", e),
             }
-            Ok(file) => {
-                // lets open our file and start reading through it.
-                let reader = BufReader::new(file);
-
-                // create our vectors for final model data
-                let mut our_model = ObjData {
-                    vertices: Vec::new(),
-                    texture: Vec::new(),
-                    normal: Vec::new(),
-                    indice: Vec::new(),
-                };
-
-                // create temporrary structures for holding data we read in from
-                // model file (obj)
-
-                let mut vertices: Vec<glam::Vec3> = Vec::new();
-                let mut textures: Vec<glam::Vec2> = Vec::new();
-                let mut normals: Vec<glam::Vec3> = Vec::new();
-                let mut indicies_to_process: Vec<String> = Vec::new();
-
-                // Iterate over the lines of the file
-                for line_from_file in reader.lines() {
-                    //                    println!("{:?}", line_from_file);
-                    let line = line_from_file.unwrap();
-                    if line.is_empty() {
-                        continue;
-                    }
-                    let current_line = Loader::split_string_to_vec(&line);
-
-                    // check to see if we have vector co-ordinates
-                    if line.starts_with("v ") {
-                        let x: f32 = current_line[1].parse().unwrap();
-                        let y: f32 = current_line[2].parse().unwrap();
-                        let z: f32 = current_line[3].parse().unwrap();
-
-                        //todo : store vertex coorrdinates
-                        vertices.push(glam::Vec3::new(x, y, z));
-                    }
-                    if line.starts_with("vt") {
-                        let x: f32 = current_line[1].parse().unwrap();
-                        let y: f32 = current_line[2].parse().unwrap();
-                        //todo : store texture coordinates
-                        textures.push(glam::Vec2::new(x, y));
-                    }
-
-                    if line.starts_with("vn") {
-                        let x: f32 = current_line[1].parse().unwrap();
-                        let y: f32 = current_line[2].parse().unwrap();
-                        let z: f32 = current_line[3].parse().unwrap();
-                        //todo : store normals coorrdinates
-                        normals.push(glam::Vec3::new(x, y, z));
-                    }
-                    if line.starts_with("f ") {
-                        let mut x = 1;
-                        while x < current_line.len() {
-                            let entry = current_line[x].clone();
-                            indicies_to_process.push(entry.clone());
-                            x += 1;
-                        }
-                    }
-                }
+        }
 
-                // now we start mapping
-                // now process indexices.  for each unique combination of indexices
-                // we will build the vertices/normals and texture coordinates
-                // step one is to get a lsit of unique cobinations
+        texture.shine_damper = material.get_shine_damper();
+        texture.relfectivity = material.get_reflectivity();
+        texture.has_transparency = material.has_transparency();
+        texture
+    }
 
-                let mut my_map = StringMap::new();
-                for x in indicies_to_process.clone() {
-                    // build a unique list of vertices / texture / normals combinations
-                    // these will be used to build our final matrices below
-                    my_map.add(&x);
-                }
+    /// Externally visible loader that converts ObjData into a RawModel
+    pub fn load_3d_model(&mut self, filename: &str) -> Result<RawModel, ObjError> {
+        let model_data: ObjData = obj_parser::load_obj_file(filename)?;
+        Ok(self.upload_mesh(&model_data.to_vertices(), &model_data.indice))
+    }
 
-                // now we store results into indidvual arrays in the correct order
-                for item in indicies_to_process.clone() {
-                    let current_indice = Loader::split_string_to_vec_using_slash(&item);
-                    // first item = vertex, 2nd is texture and third is normal
-                    let vertex_index = current_indice[0].parse::<usize>().unwrap();
-                    let texture_index = current_indice[1].parse::<usize>().unwrap();
-                    let normal_index = current_indice[2].parse::<usize>().unwrap(); //println!("{}  {:?}", vertex_index, vertices[vertex_index - 1]);
-                    our_model.vertices.push(vertices[vertex_index - 1].x);
-                    our_model.vertices.push(vertices[vertex_index - 1].y);
-                    our_model.vertices.push(vertices[vertex_index - 1].z);
-                    our_model.texture.push(textures[texture_index - 1].x);
-                    our_model.texture.push(textures[texture_index - 1].y);
-                    our_model.normal.push(normals[normal_index - 1].x);
-                    our_model.normal.push(normals[normal_index - 1].y);
-                    our_model.normal.push(normals[normal_index - 1].z);
-                    let indice_count = (our_model.vertices.len() / 3) - 1;
-                    our_model.indice.push(indice_count as u32);
-                }
-                return our_model;
-            }
-        }
+    /// like load_3d_model, with levels of detail simplified at load time
+    pub fn load_3d_model_with_lods(
+        &mut self,
+        filename: &str,
+        settings: &LodSettings,
+    ) -> Result<RawModel, ObjError> {
+        let model_data: ObjData = obj_parser::load_obj_file(filename)?;
+        let lods = model_data.generate_lods(settings);
+        Ok(self.upload_mesh_with_lods(&model_data.to_vertices(), &lods))
     }
 
-    /// externally visible loader
-    ///
+    /// loads an obj file through its binary cache, see CachedMesh::load_or_convert
+    pub fn load_3d_model_cached(&mut self, filename: &str) -> Result<RawModel, MeshCacheError> {
+        let mesh = CachedMesh::load_or_convert(filename)?;
+        Ok(self.upload_cached_mesh(&mesh))
+    }
 
-    /// Externally visible loader that converts ObjData into a RawModel
-    pub fn load_3d_model(&mut self, filename: &str) -> RawModel {
-        let model_data: ObjData = Loader::load_obj_file(filename);
-
-        // model_data has flat Vec<f32> for vertices, texture coords, and normals.
-        // We need to interlace them into a single Vec<Vertex>.
-        let mut vertices: Vec<Vertex> = Vec::new();
-        let num_vertices = model_data.vertices.len() / 3;
-
-        for i in 0..num_vertices {
-            vertices.push(Vertex {
-                position: [
-                    model_data.vertices[i * 3],
-                    model_data.vertices[i * 3 + 1],
-                    model_data.vertices[i * 3 + 2],
-                ],
-                tex_coords: [model_data.texture[i * 2], model_data.texture[i * 2 + 1]],
-                normal: [
-                    model_data.normal[i * 3],
-                    model_data.normal[i * 3 + 1],
-                    model_data.normal[i * 3 + 2],
-                ],
-            });
-        }
+    /// uploads a .mesh file already in memory, such as ModelAsset::mesh
+    pub fn load_3d_model_from_bytes(&mut self, bytes: &[u8]) -> Result<RawModel, MeshCacheError> {
+        let mesh = CachedMesh::from_bytes(bytes)?;
+        Ok(self.upload_cached_mesh(&mesh))
+    }
 
+    /// uploads a cached mesh, its arrays go into the buffers as they are
+    pub fn upload_cached_mesh(&self, mesh: &CachedMesh) -> RawModel {
         let vertex_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(&vertices), // Pass a reference to the slice
+                contents: &mesh.vertex_bytes,
                 usage: wgpu::BufferUsages::VERTEX,
             });
-
-        // Convert indices to u16 to match the format used in renderer.rs
-        let indices_u16: Vec<u16> = model_data.indice.iter().map(|&v| v as u16).collect();
-
         let index_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&indices_u16), // Pass a reference to the slice
+                contents: &mesh.index_bytes,
                 usage: wgpu::BufferUsages::INDEX,
             });
 
         RawModel {
+            id: RawModel::next_id(),
+            vertex_buffer,
+            index_buffer,
+            index_format: if mesh.index_size == 2 {
+                wgpu::IndexFormat::Uint16
+            } else {
+                wgpu::IndexFormat::Uint32
+            },
+            num_vertices: mesh.vertex_count,
+            num_indices: mesh.index_count,
+            lods: mesh.lods.clone(),
+            aabb: mesh.aabb,
+            bounding_sphere: mesh.bounding_sphere,
+            users: Default::default(),
+        }
+    }
+
+    ///
+    /// uploads interleaved vertices and indices to the gpu.
+    /// indices are stored as u16 when every vertex can be addressed with one,
+    /// otherwise as u32
+    ///
+    pub fn upload_mesh(&self, vertices: &[Vertex], indices: &[u32]) -> RawModel {
+        self.upload_mesh_with_lods(vertices, &[indices.to_vec()])
+    }
+
+    ///
+    /// like upload_mesh, with one index list per level of detail, full detail
+    /// first. the lists go one after another into a single index buffer
+    ///
+    pub fn upload_mesh_with_lods(&self, vertices: &[Vertex], lods: &[Vec<u32>]) -> RawModel {
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let indices = lods.concat();
+        let index_format = RawModel::index_format_for(vertices.len());
+        let index_buffer = match index_format {
+            wgpu::IndexFormat::Uint16 => {
+                let indices_u16: Vec<u16> = indices.iter().map(|&v| v as u16).collect();
+                self.device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Index Buffer"),
+                        contents: bytemuck::cast_slice(&indices_u16),
+                        usage: wgpu::BufferUsages::INDEX,
+                    })
+            }
+            wgpu::IndexFormat::Uint32 => {
+                self.device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Index Buffer"),
+                        contents: bytemuck::cast_slice(&indices),
+                        usage: wgpu::BufferUsages::INDEX,
+                    })
+            }
+        };
+
+        let (aabb, bounding_sphere) = vertex_bounds(vertices);
+        RawModel {
+            id: RawModel::next_id(),
             vertex_buffer,
             index_buffer,
+            index_format,
             num_vertices: vertices.len() as u32,
-            num_indices: indices_u16.len() as u32,
+            num_indices: indices.len() as u32,
+            lods: Lod::ranges(lods),
+            aabb,
+            bounding_sphere,
+            users: Default::default(),
         }
     }
 }
@@ -2,10 +2,48 @@
 /// main shader module
 ///
 ///
-use crate::game_engine::loader::Vertex;
+use std::{fmt, path::PathBuf};
+
+use crate::game_engine::{
+    depth_texture::DepthSettings,
+    loader::VertexLayout,
+    obj_parser::Vertex,
+    renderer::{EntityInstance, Renderer},
+};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io(PathBuf, std::io::Error),
+    /// the wgsl did not compile, or does not fit the pipeline
+    Compile(PathBuf, wgpu::Error),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ShaderError::Compile(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io(_, e) => Some(e),
+            ShaderError::Compile(_, e) => Some(e),
+        }
+    }
+}
 
 pub struct MainShader {
     pub render_pipeline: wgpu::RenderPipeline,
+    // kept so the pipeline can be rebuilt when the shader is reloaded
+    format: wgpu::TextureFormat,
+    depth: DepthSettings,
+    texture_layout: wgpu::BindGroupLayout,
+    uniform_layout: wgpu::BindGroupLayout,
+    lighting_layout: wgpu::BindGroupLayout,
 }
 
 impl MainShader {
@@ -14,34 +52,92 @@ impl MainShader {
         config: &wgpu::SurfaceConfiguration,
         texture_layout: &wgpu::BindGroupLayout,
         uniform_layout: &wgpu::BindGroupLayout,
+        depth: DepthSettings,
     ) -> Self {
+        // the same layout the renderer builds its lighting bind group with
+        let lighting_layout = Renderer::create_lighting_layout(device);
+        let render_pipeline = MainShader::create_pipeline(
+            device,
+            config.format,
+            &[texture_layout, uniform_layout, &lighting_layout],
+            &depth,
+            include_str!("../shader.wgsl"),
+        );
+
+        Self {
+            render_pipeline,
+            format: config.format,
+            depth,
+            texture_layout: texture_layout.clone(),
+            uniform_layout: uniform_layout.clone(),
+            lighting_layout,
+        }
+    }
+
+    ///
+    /// rebuilds the pipeline from a wgsl file on disk. if the file can not be
+    /// read or does not compile the error is returned and the current pipeline
+    /// is left alone
+    ///
+    pub fn reload(&mut self, device: &wgpu::Device, path: &str) -> Result<(), ShaderError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| ShaderError::Io(PathBuf::from(path), e))?;
+
+        // catch the validation errors instead of letting wgpu panic on them
+        let error_scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let render_pipeline = MainShader::create_pipeline(
+            device,
+            self.format,
+            &[
+                &self.texture_layout,
+                &self.uniform_layout,
+                &self.lighting_layout,
+            ],
+            &self.depth,
+            &source,
+        );
+        if let Some(error) = pollster::block_on(error_scope.pop()) {
+            return Err(ShaderError::Compile(PathBuf::from(path), error));
+        }
+
+        self.render_pipeline = render_pipeline;
+        Ok(())
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        depth: &DepthSettings,
+        source: &str,
+    ) -> wgpu::RenderPipeline {
         // LOAD THE SHADER MODULE HERE
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[texture_layout, uniform_layout],
+                bind_group_layouts,
                 immediate_size: 0,
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
+                buffers: &[Vertex::desc(), EntityInstance::desc()],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -53,12 +149,10 @@ impl MainShader {
                 cull_mode: Some(wgpu::Face::Back),
                 ..Default::default()
             },
-            depth_stencil: None,
+            depth_stencil: Some(depth.depth_stencil_state()),
             multisample: wgpu::MultisampleState::default(),
             multiview_mask: None,
             cache: None,
-        });
-
-        Self { render_pipeline }
+        })
     }
 }
@@ -1,50 +1,186 @@
 use super::{
-    camera::Camera, entity::Entity, light::Light, renderer::Renderer, textured_model::TexturedModel,
+    camera::Camera,
+    depth_texture::{DepthSettings, DepthTexture},
+    entity::Entity,
+    light::{Light, LightingUniforms},
+    main_shader::{MainShader, ShaderError},
+    renderer::Renderer,
+    terrain::Terrain,
+    terrain_renderer::TerrainRenderer,
+    textured_model::TexturedModel,
 };
 
 use std::collections::HashMap;
 
+///
+/// picks a level of detail from how big a model looks. the screen size is
+/// the height its bounding sphere covers, 1.0 being the whole screen. level 1
+/// starts at `first_switch` and each halving of the size after that drops
+/// another level
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LodSelection {
+    pub first_switch: f32,
+    /// added to the level, positive for coarser levels sooner
+    pub bias: f32,
+    /// how far past a switch, in levels, the size has to go before the level
+    /// changes, so a model sat on a switch does not pop back and forth
+    pub hysteresis: f32,
+}
+
+impl Default for LodSelection {
+    fn default() -> Self {
+        Self {
+            first_switch: 0.25,
+            bias: 0.0,
+            hysteresis: 0.15,
+        }
+    }
+}
+
+impl LodSelection {
+    /// the level to draw out of `count`, given the one drawn last frame
+    pub fn select(&self, screen_size: f32, current: usize, count: usize) -> usize {
+        let coarsest = count.saturating_sub(1);
+        let level = (self.first_switch / screen_size).log2() + 1.0 + self.bias;
+        let current = current.min(coarsest) as f32;
+        let coarser = (level - self.hysteresis).floor();
+        let finer = (level + self.hysteresis).floor();
+        let chosen = if coarser > current {
+            coarser
+        } else if finer < current {
+            finer
+        } else {
+            current
+        };
+        chosen.clamp(0.0, coarsest as f32) as usize
+    }
+}
+
 pub struct MasterRenderer {
     renderer: Renderer,
     entities: HashMap<TexturedModel, Vec<Entity>>,
+    entity_count: usize,
+    terrain_renderer: TerrainRenderer,
+    terrains: Vec<Terrain>,
+    depth_texture: DepthTexture,
+    pub lod_selection: LodSelection,
+    /// the least light any entity or terrain gets, 0.0 leaves the side facing away black
+    pub ambient: f32,
 }
 
 impl MasterRenderer {
-    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+    ///
+    /// `depth` has to be what the MainShader drawn with was built with,
+    /// the depth texture is made the size of the surface
+    ///
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layout: &wgpu::BindGroupLayout,
+        depth: DepthSettings,
+    ) -> Self {
         Self {
             entities: HashMap::new(),
+            entity_count: 0,
             renderer: Renderer::new(device, layout),
+            terrain_renderer: TerrainRenderer::new(device, config.format, depth),
+            terrains: Vec::new(),
+            depth_texture: DepthTexture::new(device, config.width, config.height, depth),
+            lod_selection: LodSelection::default(),
+            ambient: 0.2,
+        }
+    }
+
+    /// call when the surface is reconfigured to a new size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.depth_texture.resize(device, width, height);
+    }
+
+    /// the depth attachment the pass passed to render has to be begun with
+    pub fn depth_stencil_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        self.depth_texture.attachment()
+    }
+
+    /// picks each entity's level of detail for this frame, call before add_entity
+    pub fn update_lods(&self, camera: &Camera, entities: &mut [Entity]) {
+        for entity in entities {
+            let sphere = entity.get_world_bounding_sphere();
+            let distance = sphere.center.distance(camera.get_position());
+            let screen_size = camera.projection.screen_size(sphere.radius, distance);
+            entity.lod_level = self.lod_selection.select(
+                screen_size,
+                entity.lod_level,
+                entity.model.model.lods.len(),
+            );
         }
     }
 
     pub fn clear_entities(&mut self) {
         self.entities.clear();
+        self.entity_count = 0;
+    }
+
+    pub fn clear_terrains(&mut self) {
+        self.terrains.clear();
     }
 
     pub fn render<'a>(
         &'a self,
         render_pass: &mut wgpu::RenderPass<'a>,
         queue: &wgpu::Queue,
-        _sun: &Light,
+        shader: &'a MainShader,
+        sun: &Light,
         camera: &Camera,
     ) {
-        let view_matrix = camera.build_view_matrix();
-
-        for (textured_model, entity_list) in &self.entities {
-            // Bind model resources once
-            self.renderer
-                .bind_textured_model(render_pass, textured_model);
-            // Render all entities using this model
-            self.renderer
-                .render_entities(render_pass, entity_list, queue, &view_matrix);
-        }
+        // once a frame, both renderers draw with the same camera
+        let view_projection = camera.build_projection_matrix() * camera.build_view_matrix();
+
+        let lighting = LightingUniforms::new(sun, camera.get_position(), self.ambient);
+
+        // the ground first, everything else stands on it
+        self.terrain_renderer.render(
+            render_pass,
+            &self.terrains,
+            queue,
+            &view_projection,
+            &lighting,
+        );
+
+        render_pass.set_pipeline(&shader.render_pipeline);
+        // one instanced draw per model and level of detail
+        self.renderer.render(
+            render_pass,
+            &self.entities,
+            queue,
+            &view_projection,
+            &lighting,
+        );
     }
 
-    pub fn add_entity(&mut self, entity: Entity) {
+    pub fn add_entity(&mut self, mut entity: Entity) {
+        // entities whose assets are still loading are left out until they are ready
+        if !entity.update_pending() {
+            return;
+        }
         // Use the public model field from textured_model.rs
         self.entities
             .entry(entity.model.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(entity);
+        self.entity_count += 1;
+        self.renderer
+            .reserve(self.entity_count, self.entities.len());
+    }
+
+    /// rebuilds the terrain pipeline from a changed wgsl file, see MainShader::reload
+    pub fn reload_terrain_shader(&mut self, path: &str) -> Result<(), ShaderError> {
+        self.terrain_renderer.reload_shader(path)
+    }
+
+    /// terrains are cheap to clone, the heights are shared
+    pub fn add_terrain(&mut self, terrain: Terrain) {
+        self.terrains.push(terrain);
+        self.terrain_renderer.reserve(self.terrains.len());
     }
 }
@@ -1,10 +1,29 @@
+pub mod asset_manager;
+pub mod assets;
+pub mod background_loader;
 pub mod camera; // Added
+pub mod depth_texture;
 pub mod entity; // Added
+pub mod gltf_loader;
+pub mod hot_reload;
 pub mod light; // Added
 pub mod loader;
 pub mod main_shader;
 pub mod master_renderer;
 pub mod model_texture;
+pub mod primitives;
+pub mod projection;
 pub mod raw_model;
 pub mod renderer; // Added
+pub mod terrain;
+pub mod terrain_renderer;
+pub mod terrain_shader;
+pub mod terrain_texture;
+pub mod texture_atlas;
+pub mod texture_data;
 pub mod textured_model; // Added
+
+// the cpu mesh code lives in its own crate so build.rs can share it
+pub use engine_mesh::{
+    bounds, material, mesh_cache, mesh_processing, mesh_simplification, obj_error, obj_parser,
+};
@@ -1,20 +1,44 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
 #[derive(Clone)]
 pub struct ModelTexture {
-    pub id: u32, // Unique ID for hashing and comparison
+    /// unique for every upload, clones share it. see ModelTexture::next_id
+    pub id: u32,
+    /// the uploaded image on its own, for anything that binds it differently
+    pub diffuse_texture_view: wgpu::TextureView,
     pub diffuse_bind_group: wgpu::BindGroup,
     pub shine_damper: f32,
     pub relfectivity: f32,
     pub number_of_rows: u32,
+    pub has_transparency: bool,
+    pub normal_map: Option<wgpu::BindGroup>,
+    /// shared by every clone, so the AssetManager can tell when nothing
+    /// draws the upload any more
+    pub(crate) users: Arc<()>,
 }
 
 impl ModelTexture {
-    pub fn new(id: u32, bind_group: wgpu::BindGroup) -> Self {
+    /// a fresh id for an uploaded texture, unique across every Loader
+    pub fn next_id() -> u32 {
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn new(id: u32, view: wgpu::TextureView, bind_group: wgpu::BindGroup) -> Self {
         Self {
             id,
+            diffuse_texture_view: view,
             diffuse_bind_group: bind_group,
             shine_damper: 1.0,
             relfectivity: 0.0,
             number_of_rows: 1,
+            has_transparency: false,
+            normal_map: None,
+            users: Arc::default(),
         }
     }
 
@@ -23,25 +47,17 @@ impl ModelTexture {
     }
 
     pub fn get_shine(&self) -> f32 {
-        self.shine_damper.clone()
+        self.shine_damper
     }
 
     pub fn get_reflectivity(&self) -> f32 {
-        self.relfectivity.clone()
+        self.relfectivity
     }
 
     pub fn get_number_of_rows(&self) -> u32 {
-        self.number_of_rows.clone()
+        self.number_of_rows
     }
 
-    pub fn clone(&self) -> ModelTexture {
-        let mut model_texture = ModelTexture::new(self.id, self.diffuse_bind_group.clone());
-        model_texture.shine_damper = self.get_shine();
-        model_texture.relfectivity = self.get_reflectivity();
-        model_texture.number_of_rows = self.get_number_of_rows();
-
-        model_texture
-    }
     /*
         pub fn cleanup(&self, gl: &GlFns) {
     // to do
@@ -0,0 +1,402 @@
+///
+/// Primitive - procedural meshes for prototypes and debug scenes
+///
+/// every shape is built as ObjData with normals and uvs, centred on the origin
+/// with +y up and counter clockwise front faces, then uploaded with
+/// Loader::load_primitive the same way load_3d_model uploads an obj file.
+/// uvs follow the texture, v = 0 is the top row of the image
+///
+use std::{
+    collections::HashMap,
+    f32::consts::{PI, TAU},
+};
+
+use glam::{Vec2, Vec3};
+
+use crate::game_engine::{loader::Loader, obj_parser::ObjData, raw_model::RawModel};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Primitive {
+    /// flat in x z facing +y, split into subdivisions x subdivisions quads
+    Plane {
+        width: f32,
+        depth: f32,
+        subdivisions: u32,
+    },
+    /// segments around the y axis, rings from pole to pole
+    UvSphere {
+        radius: f32,
+        segments: u32,
+        rings: u32,
+    },
+    /// an icosahedron with every triangle split in four `subdivisions` times,
+    /// the triangles are much more even than a UvSphere's. triangles across
+    /// the uv seam have u a little past 1, texture it with SamplerDesc::repeat
+    Icosphere { radius: f32, subdivisions: u32 },
+    /// along the y axis, with capped ends
+    Cylinder {
+        radius: f32,
+        height: f32,
+        segments: u32,
+    },
+    /// tip at +y, capped base at -y
+    Cone {
+        radius: f32,
+        height: f32,
+        segments: u32,
+    },
+    /// lying in x z. radius is to the middle of the tube, sides go around the tube
+    Torus {
+        radius: f32,
+        tube_radius: f32,
+        segments: u32,
+        sides: u32,
+    },
+    /// a cylinder of `height` along the y axis with a half sphere on each end
+    Capsule {
+        radius: f32,
+        height: f32,
+        segments: u32,
+        rings: u32,
+    },
+    /// a box, every face gets the whole texture
+    Cuboid { size: Vec3 },
+}
+
+/// adds a vertex and returns its index
+fn push_vertex(data: &mut ObjData, position: Vec3, tex_coord: Vec2, normal: Vec3) -> u32 {
+    data.vertices.extend_from_slice(&position.to_array());
+    data.texture.extend_from_slice(&tex_coord.to_array());
+    data.normal.extend_from_slice(&normal.to_array());
+    (data.vertices.len() / 3 - 1) as u32
+}
+
+///
+/// a flat grid from `origin` (the top left of the texture) along u (to the
+/// right) and v (down). the front face is the side cross(v, u) points to
+///
+fn grid(data: &mut ObjData, origin: Vec3, u: Vec3, v: Vec3, columns: u32, rows: u32) {
+    let normal = v.cross(u).normalize_or_zero();
+    let first = (data.vertices.len() / 3) as u32;
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let tex_coord = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+            let position = origin + u * tex_coord.x + v * tex_coord.y;
+            push_vertex(data, position, tex_coord, normal);
+        }
+    }
+    let stride = columns + 1;
+    for row in 0..rows {
+        for column in 0..columns {
+            let a = first + row * stride + column;
+            let b = a + stride;
+            data.indice
+                .extend_from_slice(&[a, b, b + 1, a, b + 1, a + 1]);
+        }
+    }
+}
+
+///
+/// spins a profile around the y axis. the profile is (radius, y) points with
+/// their (radius, y) normals, from the top down; v follows the length of the
+/// profile and u goes once around. the seam column is doubled so the texture
+/// does not wrap back across the last faces
+///
+fn lathe(data: &mut ObjData, profile: &[(Vec2, Vec2)], segments: u32) {
+    let mut lengths = vec![0.0];
+    for pair in profile.windows(2) {
+        lengths.push(lengths[lengths.len() - 1] + pair[0].0.distance(pair[1].0));
+    }
+    let total = lengths[lengths.len() - 1].max(f32::EPSILON);
+
+    let first = (data.vertices.len() / 3) as u32;
+    for (&(point, normal), length) in profile.iter().zip(&lengths) {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            push_vertex(
+                data,
+                Vec3::new(point.x * cos, point.y, -point.x * sin),
+                Vec2::new(u, length / total),
+                Vec3::new(normal.x * cos, normal.y, -normal.x * sin).normalize_or(Vec3::Y),
+            );
+        }
+    }
+
+    let stride = segments + 1;
+    for (row, pair) in profile.windows(2).enumerate() {
+        for segment in 0..segments {
+            let a = first + row as u32 * stride + segment;
+            let b = a + stride;
+            // no zero area triangles where the profile touches the axis
+            if pair[1].0.x > 0.0 {
+                data.indice.extend_from_slice(&[a, b, b + 1]);
+            }
+            if pair[0].0.x > 0.0 {
+                data.indice.extend_from_slice(&[a, b + 1, a + 1]);
+            }
+        }
+    }
+}
+
+/// a flat disc at height y facing +y or -y, the texture is laid over it as seen from outside
+fn cap(data: &mut ObjData, radius: f32, y: f32, up: bool, segments: u32) {
+    let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+    let centre = push_vertex(data, Vec3::new(0.0, y, 0.0), Vec2::splat(0.5), normal);
+    for segment in 0..segments {
+        let (sin, cos) = (segment as f32 / segments as f32 * TAU).sin_cos();
+        let (x, z) = (cos, -sin);
+        let v = if up { 0.5 + 0.5 * z } else { 0.5 - 0.5 * z };
+        push_vertex(
+            data,
+            Vec3::new(x * radius, y, z * radius),
+            Vec2::new(0.5 + 0.5 * x, v),
+            normal,
+        );
+    }
+    for segment in 0..segments {
+        let a = centre + 1 + segment;
+        let b = centre + 1 + (segment + 1) % segments;
+        if up {
+            data.indice.extend_from_slice(&[centre, a, b]);
+        } else {
+            data.indice.extend_from_slice(&[centre, b, a]);
+        }
+    }
+}
+
+fn icosphere(radius: f32, subdivisions: u32) -> ObjData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|p| Vec3::from_array(*p).normalize())
+    .collect();
+    let mut triangles: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // edges are shared by two triangles, both have to use the same midpoint
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push((points[a] + points[b]).normalize());
+                points.len() - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    // uvs the same way round as a UvSphere's. triangles across the seam get
+    // their own copies of the vertices with u past 1, and a pole takes the u of
+    // the middle of its triangle so the texture does not pinch sideways
+    let mut data = ObjData::default();
+    let mut copies: HashMap<(usize, u32), u32> = HashMap::new();
+    for triangle in triangles {
+        let mut uvs = triangle.map(|i| {
+            let p = points[i];
+            Vec2::new(
+                (-p.z).atan2(p.x).rem_euclid(TAU) / TAU,
+                p.y.clamp(-1.0, 1.0).acos() / PI,
+            )
+        });
+        let highest = uvs.iter().map(|uv| uv.x).fold(f32::MIN, f32::max);
+        let lowest = uvs.iter().map(|uv| uv.x).fold(f32::MAX, f32::min);
+        if highest - lowest > 0.5 {
+            for uv in uvs.iter_mut().filter(|uv| uv.x < 0.5) {
+                uv.x += 1.0;
+            }
+        }
+        for corner in 0..3 {
+            if points[triangle[corner]].y.abs() > 1.0 - 1e-6 {
+                uvs[corner].x = (uvs[(corner + 1) % 3].x + uvs[(corner + 2) % 3].x) / 2.0;
+            }
+        }
+        for (&i, uv) in triangle.iter().zip(uvs) {
+            let index = *copies
+                .entry((i, uv.x.to_bits()))
+                .or_insert_with(|| push_vertex(&mut data, points[i] * radius, uv, points[i]));
+            data.indice.push(index);
+        }
+    }
+    data
+}
+
+impl Primitive {
+    /// builds the mesh on the cpu, counts below the minimum for a shape are raised to it
+    pub fn to_obj_data(&self) -> ObjData {
+        let mut data = ObjData::default();
+        match *self {
+            Primitive::Plane {
+                width,
+                depth,
+                subdivisions,
+            } => {
+                let subdivisions = subdivisions.max(1);
+                grid(
+                    &mut data,
+                    Vec3::new(-width / 2.0, 0.0, -depth / 2.0),
+                    Vec3::X * width,
+                    Vec3::Z * depth,
+                    subdivisions,
+                    subdivisions,
+                );
+            }
+            Primitive::UvSphere {
+                radius,
+                segments,
+                rings,
+            } => {
+                let rings = rings.max(2);
+                let profile: Vec<(Vec2, Vec2)> = (0..=rings)
+                    .map(|ring| {
+                        let (sin, cos) = (ring as f32 / rings as f32 * PI).sin_cos();
+                        let normal = Vec2::new(sin.max(0.0), cos);
+                        (normal * radius, normal)
+                    })
+                    .collect();
+                lathe(&mut data, &profile, segments.max(3));
+            }
+            Primitive::Icosphere {
+                radius,
+                subdivisions,
+            } => data = icosphere(radius, subdivisions),
+            Primitive::Cylinder {
+                radius,
+                height,
+                segments,
+            } => {
+                let segments = segments.max(3);
+                let top = height / 2.0;
+                lathe(
+                    &mut data,
+                    &[
+                        (Vec2::new(radius, top), Vec2::X),
+                        (Vec2::new(radius, -top), Vec2::X),
+                    ],
+                    segments,
+                );
+                cap(&mut data, radius, top, true, segments);
+                cap(&mut data, radius, -top, false, segments);
+            }
+            Primitive::Cone {
+                radius,
+                height,
+                segments,
+            } => {
+                let segments = segments.max(3);
+                let top = height / 2.0;
+                let normal = Vec2::new(height, radius).normalize_or(Vec2::Y);
+                lathe(
+                    &mut data,
+                    &[
+                        (Vec2::new(0.0, top), normal),
+                        (Vec2::new(radius, -top), normal),
+                    ],
+                    segments,
+                );
+                cap(&mut data, radius, -top, false, segments);
+            }
+            Primitive::Torus {
+                radius,
+                tube_radius,
+                segments,
+                sides,
+            } => {
+                let sides = sides.max(3);
+                // from the top of the tube, outwards and round underneath
+                let profile: Vec<(Vec2, Vec2)> = (0..=sides)
+                    .map(|side| {
+                        let (sin, cos) = (PI / 2.0 - side as f32 / sides as f32 * TAU).sin_cos();
+                        let normal = Vec2::new(cos, sin);
+                        (Vec2::new(radius, 0.0) + normal * tube_radius, normal)
+                    })
+                    .collect();
+                lathe(&mut data, &profile, segments.max(3));
+            }
+            Primitive::Capsule {
+                radius,
+                height,
+                segments,
+                rings,
+            } => {
+                // rings is per half sphere, the straight part joins the two equators
+                let rings = rings.max(1);
+                let top = height / 2.0;
+                let mut profile = Vec::new();
+                for (centre, start) in [(top, 0.0), (-top, PI / 2.0)] {
+                    for ring in 0..=rings {
+                        let (sin, cos) = (start + ring as f32 / rings as f32 * PI / 2.0).sin_cos();
+                        let normal = Vec2::new(sin.max(0.0), cos);
+                        profile.push((normal * radius + Vec2::new(0.0, centre), normal));
+                    }
+                }
+                lathe(&mut data, &profile, segments.max(3));
+            }
+            Primitive::Cuboid { size } => {
+                // u is to the right and v down as each face is seen from outside
+                for (normal, up) in [
+                    (Vec3::X, Vec3::Y),
+                    (Vec3::NEG_X, Vec3::Y),
+                    (Vec3::Y, Vec3::NEG_Z),
+                    (Vec3::NEG_Y, Vec3::Z),
+                    (Vec3::Z, Vec3::Y),
+                    (Vec3::NEG_Z, Vec3::Y),
+                ] {
+                    let right = (-normal).cross(up);
+                    let u = right * right.abs().dot(size);
+                    let v = -up * up.abs().dot(size);
+                    let centre = normal * normal.abs().dot(size) / 2.0;
+                    grid(&mut data, centre - u / 2.0 - v / 2.0, u, v, 1, 1);
+                }
+            }
+        }
+        data
+    }
+}
+
+impl Loader {
+    /// builds a primitive and uploads it like a loaded model
+    pub fn load_primitive(&self, primitive: Primitive) -> RawModel {
+        let data = primitive.to_obj_data();
+        self.upload_mesh(&data.to_vertices(), &data.indice)
+    }
+}
@@ -0,0 +1,135 @@
+///
+/// projection - the lens of a camera, from view space to clip space
+///
+/// with reverse_z the near plane is at depth 1 and the far plane at 0, which
+/// spreads float precision far more evenly over the distance. the depth
+/// buffer then has to be cleared to 0 and tested with Greater, which is what
+/// Projection::depth_settings gives
+///
+use glam::Mat4;
+
+use crate::game_engine::depth_texture::DepthSettings;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProjectionMode {
+    Perspective {
+        /// vertical field of view in radians
+        fov_y: f32,
+        /// None puts the far plane at infinity
+        far: Option<f32>,
+    },
+    Orthographic {
+        /// world units shown from the bottom of the view to the top
+        height: f32,
+        far: f32,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Projection {
+    pub mode: ProjectionMode,
+    /// width over height of the viewport, see set_viewport
+    pub aspect: f32,
+    pub near: f32,
+    pub reverse_z: bool,
+}
+
+impl Default for Projection {
+    /// 45 degrees, 0.1 to 100 units, for a 16:9 viewport until told otherwise
+    fn default() -> Self {
+        Projection::perspective(45.0f32.to_radians(), 16.0 / 9.0, 0.1, Some(100.0))
+    }
+}
+
+impl Projection {
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: Option<f32>) -> Projection {
+        Projection {
+            mode: ProjectionMode::Perspective { fov_y, far },
+            aspect,
+            near,
+            reverse_z: false,
+        }
+    }
+
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Projection {
+        Projection {
+            mode: ProjectionMode::Orthographic { height, far },
+            aspect,
+            near,
+            reverse_z: false,
+        }
+    }
+
+    /// keeps the aspect in step with the surface, call from resize
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// right handed, depth 0 to 1 or 1 to 0 with reverse_z
+    pub fn matrix(&self) -> Mat4 {
+        match self.mode {
+            ProjectionMode::Perspective { fov_y, far: None } => {
+                if self.reverse_z {
+                    Mat4::perspective_infinite_reverse_rh(fov_y, self.aspect, self.near)
+                } else {
+                    Mat4::perspective_infinite_rh(fov_y, self.aspect, self.near)
+                }
+            }
+            ProjectionMode::Perspective {
+                fov_y,
+                far: Some(far),
+            } => {
+                let (near, far) = self.depth_range(far);
+                Mat4::perspective_rh(fov_y, self.aspect, near, far)
+            }
+            ProjectionMode::Orthographic { height, far } => {
+                let (near, far) = self.depth_range(far);
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
+    }
+
+    /// the planes to hand glam, swapping them maps near to 1 and far to 0
+    fn depth_range(&self, far: f32) -> (f32, f32) {
+        if self.reverse_z {
+            (far, self.near)
+        } else {
+            (self.near, far)
+        }
+    }
+
+    ///
+    /// how much of the viewport's height a sphere `distance` away covers,
+    /// 1.0 being all of it. an orthographic view shows it the same size at
+    /// any distance
+    ///
+    pub fn screen_size(&self, radius: f32, distance: f32) -> f32 {
+        match self.mode {
+            ProjectionMode::Perspective { fov_y, .. } => radius / ((fov_y / 2.0).tan() * distance),
+            ProjectionMode::Orthographic { height, .. } => 2.0 * radius / height,
+        }
+    }
+
+    /// the depth buffer setup this projection's depth range needs
+    pub fn depth_settings(&self) -> DepthSettings {
+        if self.reverse_z {
+            DepthSettings {
+                compare: wgpu::CompareFunction::Greater,
+                ..DepthSettings::default()
+            }
+        } else {
+            DepthSettings::default()
+        }
+    }
+}
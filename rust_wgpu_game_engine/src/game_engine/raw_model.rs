@@ -1,11 +1,54 @@
 ///
 /// RawModel - structure and class used to store raw model
 ///
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use crate::game_engine::{
+    bounds::{Aabb, BoundingSphere},
+    mesh_simplification::Lod,
+};
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
 
 #[derive(Clone)]
 pub struct RawModel {
+    /// unique for every upload, clones share it. see RawModel::next_id
+    pub id: u32,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
+    pub index_format: wgpu::IndexFormat,
     pub num_vertices: u32,
     pub num_indices: u32,
+    /// full detail first, every level shares the vertex buffer. never empty
+    pub lods: Vec<Lod>,
+    /// bounds of the vertices in model space
+    pub aabb: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    /// shared by every clone, so the AssetManager can tell when nothing
+    /// draws the upload any more
+    pub(crate) users: Arc<()>,
+}
+
+impl RawModel {
+    /// a fresh id for an uploaded mesh, unique across every Loader
+    pub fn next_id() -> u32 {
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// smallest index format that can address `vertex_count` vertices
+    pub fn index_format_for(vertex_count: usize) -> wgpu::IndexFormat {
+        if vertex_count <= u16::MAX as usize {
+            wgpu::IndexFormat::Uint16
+        } else {
+            wgpu::IndexFormat::Uint32
+        }
+    }
+
+    /// the given level of detail, or the coarsest one there is
+    pub fn get_lod(&self, level: usize) -> Lod {
+        self.lods[level.min(self.lods.len() - 1)]
+    }
 }
@@ -1,46 +1,210 @@
 use super::entity::Entity;
+use super::light::LightingUniforms;
+use super::mesh_simplification::Lod;
 use super::textured_model::TexturedModel;
 use glam::Mat4;
+use std::ops::Range;
 
 
 
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct EntityUniforms {
-    transform: [[f32; 4]; 4],
-    tex_offset: [f32; 2],
-    num_rows: f32,
-    _padding: f32, // Added for 16-byte alignment (vec2 + float + padding = 16 bytes)
+struct CameraUniforms {
+    view_projection: [[f32; 4]; 4],
+}
+
+/// what each copy of a model drawn in one instanced call gets, see EntityInstance::desc
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EntityInstance {
+    pub model: [[f32; 4]; 4],
+    pub tex_offset: [f32; 2],
+    pub tex_scale: [f32; 2], // size of the atlas cell or region
+    /// multiplied with the texture colour
+    pub tint: [f32; 4],
+}
+
+impl EntityInstance {
+    pub fn from_entity(entity: &Entity) -> Self {
+        Self {
+            model: entity.create_transformation_matrix().to_cols_array_2d(),
+            tex_offset: entity.get_texture_offset().into(), // Uses logic from entity.rs
+            tex_scale: entity.get_texture_scale().into(),
+            tint: entity.tint.into(),
+        }
+    }
+
+    ///
+    /// the second vertex buffer of the main shader, stepped once per instance.
+    /// locations carry on after Vertex::desc, the matrix takes one per column
+    ///
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x4,
+            7 => Float32x2,
+            8 => Float32x2,
+            9 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<EntityInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
 }
 
 pub struct Renderer {
-    pub transform_buffer: wgpu::Buffer,
-    pub transform_bind_group: wgpu::BindGroup,
+    pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+    pub instance_buffer: wgpu::Buffer,
+    /// one LightingUniforms per batch, each at a multiple of lighting_stride
+    pub lighting_buffer: wgpu::Buffer,
+    pub lighting_bind_group: wgpu::BindGroup,
+    device: wgpu::Device,
+    lighting_layout: wgpu::BindGroupLayout,
+    lighting_stride: u64,
+    /// how many instances fit in instance_buffer
+    capacity: usize,
+    /// how many batches fit in lighting_buffer
+    batch_capacity: usize,
 }
 
 impl Renderer {
 pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
-        
-        let transform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Transform Buffer"),
-            size: std::mem::size_of::<EntityUniforms>() as u64,
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: std::mem::size_of::<CameraUniforms>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
-                resource: transform_buffer.as_entire_binding(),
+                resource: camera_buffer.as_entire_binding(),
             }],
-            label: Some("transform_bind_group"),
+            label: Some("camera_bind_group"),
         });
 
+        // dynamic offsets have to be a multiple of the device's alignment
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let lighting_stride =
+            (std::mem::size_of::<LightingUniforms>() as u64).div_ceil(alignment) * alignment;
+        let lighting_layout = Renderer::create_lighting_layout(device);
+        let (lighting_buffer, lighting_bind_group) =
+            Renderer::create_lighting_buffer(device, &lighting_layout, lighting_stride, 1);
+
         Self {
-            transform_buffer,
-            transform_bind_group,
+            camera_buffer,
+            camera_bind_group,
+            instance_buffer: Renderer::create_instance_buffer(device, 1),
+            lighting_buffer,
+            lighting_bind_group,
+            device: device.clone(),
+            lighting_layout,
+            lighting_stride,
+            capacity: 1,
+            batch_capacity: 1,
+        }
+    }
+
+    /// group 1 of the main shader: the view and projection, the same for every entity
+    pub fn create_uniform_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("uniform_layout"),
+        })
+    }
+
+    ///
+    /// group 2 of the main shader: the sun, the camera position and the
+    /// material of the batch being drawn, picked with a dynamic offset
+    ///
+    pub fn create_lighting_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<LightingUniforms>() as u64,
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("lighting_layout"),
+        })
+    }
+
+    fn create_lighting_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        stride: u64,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lighting Buffer"),
+            size: stride * capacity as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<LightingUniforms>() as u64),
+                }),
+            }],
+            label: Some("lighting_bind_group"),
+        });
+        (buffer, bind_group)
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (std::mem::size_of::<EntityInstance>() * capacity) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    ///
+    /// makes sure there is room for `count` entities in `batches` textured
+    /// models, growing the buffers by doubling
+    ///
+    pub fn reserve(&mut self, count: usize, batches: usize) {
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.instance_buffer = Renderer::create_instance_buffer(&self.device, self.capacity);
+        }
+        if batches > self.batch_capacity {
+            self.batch_capacity = batches.next_power_of_two();
+            (self.lighting_buffer, self.lighting_bind_group) = Renderer::create_lighting_buffer(
+                &self.device,
+                &self.lighting_layout,
+                self.lighting_stride,
+                self.batch_capacity,
+            );
         }
     }
 
@@ -53,46 +217,87 @@ pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
         render_pass.set_vertex_buffer(0, textured_model.model.vertex_buffer.slice(..));
         render_pass.set_index_buffer(
             textured_model.model.index_buffer.slice(..),
-            wgpu::IndexFormat::Uint16,
+            textured_model.model.index_format,
         );
         render_pass.set_bind_group(0, &textured_model.texture.diffuse_bind_group, &[]);
     }
 
-    pub fn render_entities<'a>(
+    ///
+    /// draws every batch with one instanced call per mesh, texture and level
+    /// of detail in it. all instances go up in one write, each batch's sorted
+    /// by mesh and level so they sit next to each other. writes only land when the frame is submitted,
+    /// so every instance needs its own place in the buffer, and so does the
+    /// lighting of every batch, which carries its texture's material.
+    /// reserve has to have been called for at least as many entities and batches
+    ///
+    pub fn render<'a>(
         &'a self,
         render_pass: &mut wgpu::RenderPass<'a>,
-        entities: &'a Vec<Entity>,
+        batches: impl IntoIterator<Item = (&'a TexturedModel, &'a Vec<Entity>)>,
         queue: &wgpu::Queue,
-        view_matrix: &Mat4,
+        view_projection: &Mat4,
+        lighting: &LightingUniforms,
     ) {
-        let aspect = 1.77; // Ensure this matches your window aspect ratio
-
-        // Switch to Right-Handed Perspective
-        // fov: 45 degrees, near plane: 0.1, far plane: 100.0
-        let projection = Mat4::perspective_rh(45.0f32.to_radians(), aspect, 0.1, 100.0);
+        let camera = CameraUniforms {
+            view_projection: view_projection.to_cols_array_2d(),
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[camera]));
 
-        let pv_matrix = projection * *view_matrix;
+        let mut instances: Vec<EntityInstance> = Vec::new();
+        let mut lightings: Vec<u8> = Vec::new();
+        // the model, its level of detail, its instances and the offset of its lighting
+        let mut draws: Vec<(&TexturedModel, Lod, Range<u32>, u32)> = Vec::new();
+        for (textured_model, entity_list) in batches {
+            // padded out to the stride so each batch's lighting starts on an aligned offset
+            lightings.resize(
+                lightings
+                    .len()
+                    .next_multiple_of(self.lighting_stride as usize),
+                0,
+            );
+            let lighting_offset = lightings.len() as u32;
+            lightings.extend_from_slice(bytemuck::bytes_of(
+                &lighting.with_material(&textured_model.texture),
+            ));
 
-        for entity in entities {
-            let matrix = pv_matrix * entity.create_transformation_matrix();
-//            let matrix_array: [[f32; 4]; 4] = matrix.to_cols_array_2d();
+            // a batch is keyed on the mesh and texture, so only the level of
+            // detail splits it into draws. sorted so each level is drawn once
+            let mut entity_list: Vec<&Entity> = entity_list.iter().collect();
+            entity_list
+                .sort_by_key(|entity| textured_model.model.get_lod(entity.lod_level).first_index);
 
-            let uniforms = EntityUniforms {
-                transform: matrix.to_cols_array_2d(),
-                tex_offset: entity.get_texture_offset().into(), // Uses logic from entity.rs
-                num_rows: entity.model.get_texture().get_number_of_rows() as f32,
-                _padding: 0.0,
-            };
+            let first_draw = draws.len();
+            for entity in entity_list {
+                let lod = textured_model.model.get_lod(entity.lod_level);
+                let index = instances.len() as u32;
+                match draws[first_draw..].last_mut() {
+                    Some((_, last_lod, range, _)) if *last_lod == lod => range.end = index + 1,
+                    _ => draws.push((textured_model, lod, index..index + 1, lighting_offset)),
+                }
+                instances.push(EntityInstance::from_entity(entity));
+            }
+        }
+        if instances.is_empty() {
+            return;
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        queue.write_buffer(&self.lighting_buffer, 0, &lightings);
 
-            // 4. Write the expanded struct to the buffer
-            queue.write_buffer(
-                &self.transform_buffer,
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        let mut bound: Option<(&TexturedModel, u32)> = None;
+        for (textured_model, lod, instance_range, lighting_offset) in draws {
+            // Bind model resources once
+            if bound != Some((textured_model, lighting_offset)) {
+                self.bind_textured_model(render_pass, textured_model);
+                render_pass.set_bind_group(2, &self.lighting_bind_group, &[lighting_offset]);
+                bound = Some((textured_model, lighting_offset));
+            }
+            render_pass.draw_indexed(
+                lod.first_index..lod.first_index + lod.index_count,
                 0,
-                bytemuck::cast_slice(&[uniforms]),
+                instance_range,
             );
-
-            render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
-            render_pass.draw_indexed(0..entity.model.model.num_indices, 0, 0..1);
         }
     }
 }
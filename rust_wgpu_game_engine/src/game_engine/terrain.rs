@@ -0,0 +1,263 @@
+///
+/// Terrain - a square of ground built from a heightmap or a height function
+///
+/// terrains sit on a grid of SIZE x SIZE squares, terrain (0, 0) runs from the
+/// origin to (SIZE, SIZE) in x and z. the heights are kept on the cpu so
+/// entities and the camera can be put on the ground with get_height_of_terrain
+///
+use std::{path::PathBuf, sync::Arc};
+
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::game_engine::{
+    loader::Loader, obj_parser::ObjData, raw_model::RawModel, terrain_texture::TerrainTexture,
+    texture_data::TextureError,
+};
+
+/// width and depth of every terrain
+pub const SIZE: f32 = 800.0;
+/// heightmaps go from -MAX_HEIGHT (black) to MAX_HEIGHT (white)
+pub const MAX_HEIGHT: f32 = 40.0;
+/// how many times the ground texture repeats across a terrain
+pub const DEFAULT_TILING: f32 = 40.0;
+
+#[derive(Clone)]
+pub struct Terrain {
+    x: f32,
+    z: f32,
+    /// vertices along each side
+    vertex_count: usize,
+    /// vertex_count * vertex_count heights, row by row along +z
+    heights: Arc<[f32]>,
+    pub model: RawModel,
+    /// the texture pack and blend map it is painted with
+    pub texture: TerrainTexture,
+    pub tiling: f32,
+}
+
+impl Terrain {
+    ///
+    /// builds a terrain from a greyscale image, one vertex per pixel row
+    /// and column. colour images use their luminance
+    ///
+    pub fn from_heightmap(
+        grid_x: i32,
+        grid_z: i32,
+        loader: &Loader,
+        texture: &TerrainTexture,
+        heightmap: &str,
+    ) -> Result<Terrain, TextureError> {
+        let bytes =
+            std::fs::read(heightmap).map_err(|e| TextureError::Io(PathBuf::from(heightmap), e))?;
+        let heights = Terrain::heights_from_image(&image::load_from_memory(&bytes)?);
+        Ok(Terrain::from_heights(
+            grid_x, grid_z, loader, texture, heights,
+        ))
+    }
+
+    ///
+    /// builds a terrain by asking `height` for the height at each vertex.
+    /// it is given world x and z, so neighbouring terrains built from the same
+    /// function line up
+    ///
+    pub fn from_height_fn(
+        grid_x: i32,
+        grid_z: i32,
+        loader: &Loader,
+        texture: &TerrainTexture,
+        vertex_count: usize,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Terrain {
+        let vertex_count = vertex_count.max(2);
+        let spacing = SIZE / (vertex_count - 1) as f32;
+        let (x, z) = (grid_x as f32 * SIZE, grid_z as f32 * SIZE);
+        let heights = (0..vertex_count * vertex_count)
+            .map(|i| {
+                let (column, row) = (i % vertex_count, i / vertex_count);
+                height(x + column as f32 * spacing, z + row as f32 * spacing)
+            })
+            .collect();
+        Terrain::from_heights(grid_x, grid_z, loader, texture, heights)
+    }
+
+    fn from_heights(
+        grid_x: i32,
+        grid_z: i32,
+        loader: &Loader,
+        texture: &TerrainTexture,
+        heights: Vec<f32>,
+    ) -> Terrain {
+        let vertex_count = (heights.len() as f64).sqrt() as usize;
+        let data = Terrain::generate_mesh(&heights, vertex_count);
+        let model = loader.upload_mesh(&data.to_vertices(), &data.indice);
+        Terrain {
+            x: grid_x as f32 * SIZE,
+            z: grid_z as f32 * SIZE,
+            vertex_count,
+            heights: heights.into(),
+            model,
+            texture: texture.clone(),
+            tiling: DEFAULT_TILING,
+        }
+    }
+
+    /// heights for every pixel of a square heightmap, row by row
+    pub fn heights_from_image(image: &image::DynamicImage) -> Vec<f32> {
+        let luma = image.to_luma16();
+        let vertex_count = luma.height().max(2) as usize;
+        // a non square image is stretched across the terrain
+        let scale = (luma.width() - 1) as f32 / (vertex_count - 1) as f32;
+        let mut heights = Vec::with_capacity(vertex_count * vertex_count);
+        for row in 0..vertex_count {
+            for column in 0..vertex_count {
+                let x = (column as f32 * scale).round() as u32;
+                let pixel = luma.get_pixel(x, (row as u32).min(luma.height() - 1))[0];
+                heights.push((pixel as f32 / u16::MAX as f32 * 2.0 - 1.0) * MAX_HEIGHT);
+            }
+        }
+        heights
+    }
+
+    ///
+    /// the ground mesh in terrain space, x and z from 0 to SIZE. normals come
+    /// from the neighbouring heights, uvs go from 0 to 1 across the terrain
+    /// (the renderer repeats the texture `tiling` times).
+    /// every square is split from its top right to its bottom left corner,
+    /// height_in_mesh relies on that
+    ///
+    pub fn generate_mesh(heights: &[f32], vertex_count: usize) -> ObjData {
+        let spacing = SIZE / (vertex_count - 1) as f32;
+        let height = |column: usize, row: usize| -> f32 {
+            heights[row.min(vertex_count - 1) * vertex_count + column.min(vertex_count - 1)]
+        };
+
+        let mut data = ObjData::default();
+        for row in 0..vertex_count {
+            for column in 0..vertex_count {
+                data.vertices.extend_from_slice(&[
+                    column as f32 * spacing,
+                    height(column, row),
+                    row as f32 * spacing,
+                ]);
+                data.texture.extend_from_slice(&[
+                    column as f32 / (vertex_count - 1) as f32,
+                    row as f32 / (vertex_count - 1) as f32,
+                ]);
+                // central differences, one sided at the edges
+                let (left, right) = (column.saturating_sub(1), (column + 1).min(vertex_count - 1));
+                let (up, down) = (row.saturating_sub(1), (row + 1).min(vertex_count - 1));
+                let slope_x = (height(right, row) - height(left, row)) / (right - left) as f32;
+                let slope_z = (height(column, down) - height(column, up)) / (down - up) as f32;
+                let normal = Vec3::new(-slope_x, spacing, -slope_z).normalize();
+                data.normal.extend_from_slice(&normal.to_array());
+            }
+        }
+
+        for row in 0..vertex_count - 1 {
+            for column in 0..vertex_count - 1 {
+                let top_left = (row * vertex_count + column) as u32;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + vertex_count as u32;
+                let bottom_right = bottom_left + 1;
+                data.indice.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    top_right,
+                    top_right,
+                    bottom_left,
+                    bottom_right,
+                ]);
+            }
+        }
+        data
+    }
+
+    ///
+    /// height of the ground at a world position, found on the triangle under
+    /// it so it matches what is drawn. 0 outside the terrain
+    ///
+    pub fn get_height_of_terrain(&self, world_x: f32, world_z: f32) -> f32 {
+        Terrain::height_in_mesh(
+            &self.heights,
+            self.vertex_count,
+            world_x - self.x,
+            world_z - self.z,
+        )
+    }
+
+    ///
+    /// height at a point in terrain space on the mesh generate_mesh builds
+    /// from the same heights, interpolated across the triangle the point is
+    /// in. 0 outside the terrain
+    ///
+    pub fn height_in_mesh(
+        heights: &[f32],
+        vertex_count: usize,
+        terrain_x: f32,
+        terrain_z: f32,
+    ) -> f32 {
+        if !(0.0..=SIZE).contains(&terrain_x) || !(0.0..=SIZE).contains(&terrain_z) {
+            return 0.0;
+        }
+        let spacing = SIZE / (vertex_count - 1) as f32;
+        // the far edge belongs to the last square
+        let last = (vertex_count - 2) as f32;
+        let grid_x = (terrain_x / spacing).floor().min(last);
+        let grid_z = (terrain_z / spacing).floor().min(last);
+        let x_coord = terrain_x / spacing - grid_x;
+        let z_coord = terrain_z / spacing - grid_z;
+
+        let (column, row) = (grid_x as usize, grid_z as usize);
+        let height = |column: usize, row: usize| heights[row * vertex_count + column];
+        let point = Vec2::new(x_coord, z_coord);
+        if x_coord <= 1.0 - z_coord {
+            barycentric(
+                Vec3::new(0.0, height(column, row), 0.0),
+                Vec3::new(1.0, height(column + 1, row), 0.0),
+                Vec3::new(0.0, height(column, row + 1), 1.0),
+                point,
+            )
+        } else {
+            barycentric(
+                Vec3::new(1.0, height(column + 1, row), 0.0),
+                Vec3::new(1.0, height(column + 1, row + 1), 1.0),
+                Vec3::new(0.0, height(column, row + 1), 1.0),
+                point,
+            )
+        }
+    }
+
+    /// whether a world position is over this terrain
+    pub fn contains(&self, world_x: f32, world_z: f32) -> bool {
+        (self.x..=self.x + SIZE).contains(&world_x) && (self.z..=self.z + SIZE).contains(&world_z)
+    }
+
+    pub fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn get_z(&self) -> f32 {
+        self.z
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn get_model(&self) -> RawModel {
+        self.model.clone()
+    }
+
+    pub fn create_transformation_matrix(&self) -> Mat4 {
+        Mat4::from_translation(Vec3::new(self.x, 0.0, self.z))
+    }
+}
+
+/// interpolates the y of a triangle at `point`, given in the triangle's x z
+fn barycentric(p1: Vec3, p2: Vec3, p3: Vec3, point: Vec2) -> f32 {
+    let det = (p2.z - p3.z) * (p1.x - p3.x) + (p3.x - p2.x) * (p1.z - p3.z);
+    let l1 = ((p2.z - p3.z) * (point.x - p3.x) + (p3.x - p2.x) * (point.y - p3.z)) / det;
+    let l2 = ((p3.z - p1.z) * (point.x - p3.x) + (p1.x - p3.x) * (point.y - p3.z)) / det;
+    let l3 = 1.0 - l1 - l2;
+    l1 * p1.y + l2 * p2.y + l3 * p3.y
+}
@@ -0,0 +1,148 @@
+///
+/// TerrainRenderer - draws blend mapped terrains with the terrain shader
+///
+/// every terrain gets its own uniform buffer: the writes are only carried out
+/// when the frame is submitted, so terrains sharing one buffer would all be
+/// drawn where the last one is. the sun lights the ground the way it lights
+/// entities, with the lighting group of the main shader
+///
+use glam::{Mat4, Vec2};
+
+use crate::game_engine::{
+    depth_texture::DepthSettings, light::LightingUniforms, main_shader::ShaderError,
+    renderer::Renderer, terrain::Terrain, terrain_shader::TerrainShader,
+};
+
+/// same layout as the uniforms in terrain_shader.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TerrainUniforms {
+    transform: [[f32; 4]; 4],
+    /// the terrain's place in the world, for the lighting
+    model: [[f32; 4]; 4],
+    tex_offset: [f32; 2],
+    /// the mesh uvs go from 0 to 1, this repeats the texture across the terrain
+    tex_scale: [f32; 2],
+}
+
+pub struct TerrainRenderer {
+    shader: TerrainShader,
+    device: wgpu::Device,
+    layout: wgpu::BindGroupLayout,
+    uniforms: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    /// one LightingUniforms shared by every terrain
+    lighting_buffer: wgpu::Buffer,
+    lighting_bind_group: wgpu::BindGroup,
+}
+
+impl TerrainRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, depth: DepthSettings) -> Self {
+        let layout = TerrainRenderer::create_uniform_layout(device);
+        // the main shader's layout, so both shaders read the same struct
+        let lighting_layout = Renderer::create_lighting_layout(device);
+        let lighting_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Terrain Lighting Buffer"),
+            size: std::mem::size_of::<LightingUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let lighting_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &lighting_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &lighting_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<LightingUniforms>() as u64),
+                }),
+            }],
+            label: Some("terrain_lighting_bind_group"),
+        });
+        Self {
+            shader: TerrainShader::new(device, format, &layout, &lighting_layout, depth),
+            device: device.clone(),
+            layout,
+            uniforms: Vec::new(),
+            lighting_buffer,
+            lighting_bind_group,
+        }
+    }
+
+    /// group 1 of the terrain shader, one TerrainUniforms
+    fn create_uniform_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("terrain_uniform_layout"),
+        })
+    }
+
+    pub fn reload_shader(&mut self, path: &str) -> Result<(), ShaderError> {
+        self.shader.reload(&self.device, path)
+    }
+
+    /// makes sure there is a uniform buffer for each of `count` terrains
+    pub fn reserve(&mut self, count: usize) {
+        while self.uniforms.len() < count {
+            let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Terrain Uniform Buffer"),
+                size: std::mem::size_of::<TerrainUniforms>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("terrain_uniform_bind_group"),
+            });
+            self.uniforms.push((buffer, bind_group));
+        }
+    }
+
+    /// draws the terrains, reserve has to have been called for at least as many
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        terrains: &'a [Terrain],
+        queue: &wgpu::Queue,
+        view_projection: &Mat4,
+        lighting: &LightingUniforms,
+    ) {
+        if terrains.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.shader.render_pipeline);
+        queue.write_buffer(&self.lighting_buffer, 0, bytemuck::bytes_of(lighting));
+        // the layout takes a dynamic offset, there is only the one
+        render_pass.set_bind_group(2, &self.lighting_bind_group, &[0]);
+
+        for (terrain, (buffer, bind_group)) in terrains.iter().zip(&self.uniforms) {
+            let model = terrain.create_transformation_matrix();
+            let uniforms = TerrainUniforms {
+                transform: (*view_projection * model).to_cols_array_2d(),
+                model: model.to_cols_array_2d(),
+                tex_offset: [0.0, 0.0],
+                tex_scale: Vec2::splat(terrain.tiling).into(),
+            };
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+            let model = &terrain.model;
+            render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(model.index_buffer.slice(..), model.index_format);
+            render_pass.set_bind_group(0, &terrain.texture.bind_group, &[]);
+            render_pass.set_bind_group(1, bind_group, &[]);
+            render_pass.draw_indexed(0..model.num_indices, 0, 0..1);
+        }
+    }
+}
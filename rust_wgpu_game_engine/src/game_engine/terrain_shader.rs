@@ -0,0 +1,126 @@
+///
+/// terrain shader module - the pipeline for blend mapped terrain
+///
+use std::path::PathBuf;
+
+use crate::game_engine::{
+    depth_texture::DepthSettings, loader::VertexLayout, main_shader::ShaderError,
+    obj_parser::Vertex, terrain_texture::TerrainTexture,
+};
+
+pub struct TerrainShader {
+    pub render_pipeline: wgpu::RenderPipeline,
+    // kept so the pipeline can be rebuilt when the shader is reloaded
+    format: wgpu::TextureFormat,
+    depth: DepthSettings,
+    texture_layout: wgpu::BindGroupLayout,
+    uniform_layout: wgpu::BindGroupLayout,
+    lighting_layout: wgpu::BindGroupLayout,
+}
+
+impl TerrainShader {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        uniform_layout: &wgpu::BindGroupLayout,
+        lighting_layout: &wgpu::BindGroupLayout,
+        depth: DepthSettings,
+    ) -> Self {
+        let texture_layout = TerrainTexture::create_layout(device);
+        let render_pipeline = TerrainShader::create_pipeline(
+            device,
+            format,
+            &[&texture_layout, uniform_layout, lighting_layout],
+            &depth,
+            include_str!("../terrain_shader.wgsl"),
+        );
+
+        Self {
+            render_pipeline,
+            format,
+            depth,
+            texture_layout,
+            uniform_layout: uniform_layout.clone(),
+            lighting_layout: lighting_layout.clone(),
+        }
+    }
+
+    ///
+    /// rebuilds the pipeline from a wgsl file on disk, the current pipeline is
+    /// kept if the file can not be read or does not compile
+    ///
+    pub fn reload(&mut self, device: &wgpu::Device, path: &str) -> Result<(), ShaderError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| ShaderError::Io(PathBuf::from(path), e))?;
+
+        let error_scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let render_pipeline = TerrainShader::create_pipeline(
+            device,
+            self.format,
+            &[
+                &self.texture_layout,
+                &self.uniform_layout,
+                &self.lighting_layout,
+            ],
+            &self.depth,
+            &source,
+        );
+        if let Some(error) = pollster::block_on(error_scope.pop()) {
+            return Err(ShaderError::Compile(PathBuf::from(path), error));
+        }
+
+        self.render_pipeline = render_pipeline;
+        Ok(())
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        depth: &DepthSettings,
+        source: &str,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Terrain Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Terrain Pipeline Layout"),
+                bind_group_layouts,
+                immediate_size: 0,
+            });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Terrain Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(depth.depth_stencil_state()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+///
+/// terrain textures - four tiling textures mixed by a blend map
+///
+/// the blend map is stretched once over the whole terrain. where it is red
+/// the r texture shows, green the g texture, blue the b texture, and where it
+/// is black the background. the four textures repeat `tiling` times across
+/// the terrain like a single ground texture would
+///
+/// the textures go through the AssetManager, so they are shared with anything
+/// else using the same files and hot reloading can swap them (see rebuild)
+///
+use crate::game_engine::{
+    asset_manager::{AssetManager, Handle},
+    loader::Loader,
+    model_texture::ModelTexture,
+    texture_data::{SamplerDesc, TextureError, TextureOptions},
+};
+
+/// the four textures a blend map picks between
+#[derive(Clone)]
+pub struct TerrainTexturePack {
+    pub background: Handle<ModelTexture>,
+    pub r_texture: Handle<ModelTexture>,
+    pub g_texture: Handle<ModelTexture>,
+    pub b_texture: Handle<ModelTexture>,
+}
+
+impl TerrainTexturePack {
+    /// loads four colour textures, with mipmaps as they are tiled into the distance
+    pub fn load(
+        assets: &mut AssetManager,
+        background: &str,
+        r_texture: &str,
+        g_texture: &str,
+        b_texture: &str,
+    ) -> Result<TerrainTexturePack, TextureError> {
+        let mut load = |path: &str| assets.load_texture(path, &TextureOptions::default());
+        Ok(TerrainTexturePack {
+            background: load(background)?,
+            r_texture: load(r_texture)?,
+            g_texture: load(g_texture)?,
+            b_texture: load(b_texture)?,
+        })
+    }
+}
+
+/// the view of a texture loaded with AssetManager::load_texture, which is
+/// never pending
+fn view(texture: &Handle<ModelTexture>) -> wgpu::TextureView {
+    texture
+        .get()
+        .expect("terrain textures are not loaded in the background")
+        .diffuse_texture_view
+        .clone()
+}
+
+/// a texture pack and blend map bound together, ready for the terrain shader
+#[derive(Clone)]
+pub struct TerrainTexture {
+    pub bind_group: wgpu::BindGroup,
+    pack: TerrainTexturePack,
+    blend_map: Handle<ModelTexture>,
+}
+
+impl TerrainTexture {
+    ///
+    /// group 0 of the terrain shader: the background, r, g and b textures at
+    /// bindings 0 to 3, the blend map at 4, the repeating sampler for the
+    /// four textures at 5 and the blend map's sampler at 6
+    ///
+    pub fn create_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0),
+                texture(1),
+                texture(2),
+                texture(3),
+                texture(4),
+                sampler(5),
+                sampler(6),
+            ],
+            label: Some("terrain_texture_bind_group_layout"),
+        })
+    }
+
+    ///
+    /// loads the blend map and binds it with the pack. the blend map holds
+    /// weights rather than colours, so it is read as linear
+    ///
+    pub fn new(
+        assets: &mut AssetManager,
+        pack: &TerrainTexturePack,
+        blend_map: &str,
+    ) -> Result<TerrainTexture, TextureError> {
+        let blend_map = assets.load_texture(blend_map, &TextureOptions::linear())?;
+        Ok(TerrainTexture {
+            bind_group: TerrainTexture::bind(assets.get_loader(), pack, &blend_map),
+            pack: pack.clone(),
+            blend_map,
+        })
+    }
+
+    /// true if `texture_id` is one of the five textures bound
+    pub fn uses(&self, texture_id: u32) -> bool {
+        [
+            &self.pack.background,
+            &self.pack.r_texture,
+            &self.pack.g_texture,
+            &self.pack.b_texture,
+            &self.blend_map,
+        ]
+        .into_iter()
+        .any(|texture| {
+            texture
+                .get()
+                .is_some_and(|texture| texture.id == texture_id)
+        })
+    }
+
+    /// binds the textures again, after one of them has been reloaded
+    pub fn rebuild(&mut self, loader: &mut Loader) {
+        self.bind_group = TerrainTexture::bind(loader, &self.pack, &self.blend_map);
+    }
+
+    fn bind(
+        loader: &mut Loader,
+        pack: &TerrainTexturePack,
+        blend_map: &Handle<ModelTexture>,
+    ) -> wgpu::BindGroup {
+        let tiling_sampler = loader.get_sampler(&SamplerDesc::repeat());
+        let blend_sampler = loader.get_sampler(&SamplerDesc::default());
+
+        let device = loader.get_device();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &TerrainTexture::create_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view(&pack.background)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view(&pack.r_texture)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&view(&pack.g_texture)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&view(&pack.b_texture)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&view(blend_map)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(&tiling_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Sampler(&blend_sampler),
+                },
+            ],
+            label: Some("terrain_texture_bind_group"),
+        })
+    }
+}
@@ -0,0 +1,406 @@
+///
+/// Texture atlas packer
+///
+/// grid atlases pack equal size images into a square grid that works with
+/// ModelTexture::number_of_rows and Entity::texture_index.
+/// rect atlases pack images of any size and give each one a uv region that
+/// an Entity can use instead of a grid index.
+///
+/// the manifest is a small text file, one image per line:
+///   grid <number_of_rows>          rect
+///   <name> <texture_index>         <name> <u> <v> <width> <height>
+///
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use glam::Vec2;
+use image::RgbaImage;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(PathBuf, std::io::Error),
+    Image(PathBuf, image::ImageError),
+    /// there were no images to pack
+    Empty,
+    /// grid atlases need every image to be the same size
+    SizeMismatch {
+        name: String,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    /// a manifest line could not be understood
+    Manifest(usize, String),
+    /// an image, with its padding, does not fit in an atlas of at most
+    /// `max_size` x `max_size`
+    DoesNotFit {
+        name: String,
+        max_size: u32,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            AtlasError::Image(path, e) => write!(f, "{}: {}", path.display(), e),
+            AtlasError::Empty => write!(f, "no images to pack"),
+            AtlasError::SizeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is {}x{}, every image in a grid atlas must be {}x{}",
+                name, found.0, found.1, expected.0, expected.1
+            ),
+            AtlasError::Manifest(line, text) => {
+                write!(f, "manifest line {}: can not read '{}'", line, text)
+            }
+            AtlasError::DoesNotFit { name, max_size } => write!(
+                f,
+                "{} does not fit in a {}x{} atlas",
+                name, max_size, max_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AtlasError::Io(_, e) => Some(e),
+            AtlasError::Image(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// largest atlas pack_rects makes unless told otherwise, the size every
+/// wgpu adapter supports for 2d textures
+pub const DEFAULT_MAX_ATLAS_SIZE: u32 = 8192;
+
+/// part of an atlas in uv space, 0..1 with the origin at the top left
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub offset: Vec2,
+    pub size: Vec2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AtlasManifest {
+    /// image name to texture_index
+    Grid {
+        number_of_rows: u32,
+        entries: Vec<(String, u32)>,
+    },
+    /// image name to uv region
+    Rects { entries: Vec<(String, AtlasRegion)> },
+}
+
+impl AtlasManifest {
+    /// grid index of an image, only for grid atlases
+    pub fn get_texture_index(&self, name: &str) -> Option<u32> {
+        match self {
+            AtlasManifest::Grid { entries, .. } => entries
+                .iter()
+                .find(|(entry, _)| entry == name)
+                .map(|(_, index)| *index),
+            AtlasManifest::Rects { .. } => None,
+        }
+    }
+
+    /// uv region of an image, works for both kinds of atlas
+    pub fn get_region(&self, name: &str) -> Option<AtlasRegion> {
+        match self {
+            AtlasManifest::Grid {
+                number_of_rows,
+                entries,
+            } => {
+                let rows = *number_of_rows as f32;
+                entries
+                    .iter()
+                    .find(|(entry, _)| entry == name)
+                    .map(|(_, index)| AtlasRegion {
+                        offset: Vec2::new(
+                            (index % number_of_rows) as f32 / rows,
+                            (index / number_of_rows) as f32 / rows,
+                        ),
+                        size: Vec2::splat(1.0 / rows),
+                    })
+            }
+            AtlasManifest::Rects { entries } => entries
+                .iter()
+                .find(|(entry, _)| entry == name)
+                .map(|(_, region)| *region),
+        }
+    }
+
+    /// reads a manifest written by TextureAtlas::save
+    pub fn from_path(path: &str) -> Result<AtlasManifest, AtlasError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| AtlasError::Io(PathBuf::from(path), e))?;
+        AtlasManifest::parse(&text)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        match self {
+            AtlasManifest::Grid {
+                number_of_rows,
+                entries,
+            } => {
+                text.push_str(&format!("grid {}\n", number_of_rows));
+                for (name, index) in entries {
+                    text.push_str(&format!("{} {}\n", name, index));
+                }
+            }
+            AtlasManifest::Rects { entries } => {
+                text.push_str("rect\n");
+                for (name, region) in entries {
+                    text.push_str(&format!(
+                        "{} {} {} {} {}\n",
+                        name, region.offset.x, region.offset.y, region.size.x, region.size.y
+                    ));
+                }
+            }
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<AtlasManifest, AtlasError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let bad_line =
+            |line_index: usize, line: &str| AtlasError::Manifest(line_index + 1, line.to_string());
+
+        let (header_index, header) = lines.next().ok_or(AtlasError::Empty)?;
+        let header_words: Vec<&str> = header.split_whitespace().collect();
+        match header_words.as_slice() {
+            ["grid", rows] => {
+                let number_of_rows = rows
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&rows| rows > 0)
+                    .ok_or_else(|| bad_line(header_index, header))?;
+                let cells = number_of_rows.saturating_mul(number_of_rows);
+                let mut entries = Vec::new();
+                for (line_index, line) in lines {
+                    // names can contain spaces, the index is always the last word.
+                    // an index past the last cell would give a region outside the atlas
+                    let (name, index) = line
+                        .rsplit_once(' ')
+                        .and_then(|(name, index)| Some((name, index.parse::<u32>().ok()?)))
+                        .filter(|&(_, index)| index < cells)
+                        .ok_or_else(|| bad_line(line_index, line))?;
+                    entries.push((name.to_string(), index));
+                }
+                Ok(AtlasManifest::Grid {
+                    number_of_rows,
+                    entries,
+                })
+            }
+            ["rect"] => {
+                let mut entries = Vec::new();
+                for (line_index, line) in lines {
+                    let words: Vec<&str> = line.rsplitn(5, ' ').collect();
+                    let numbers: Option<Vec<f32>> = words
+                        .iter()
+                        .take(4)
+                        .map(|w| w.parse::<f32>().ok())
+                        .collect();
+                    match (numbers, words.get(4)) {
+                        (Some(numbers), Some(name)) => entries.push((
+                            name.to_string(),
+                            AtlasRegion {
+                                offset: Vec2::new(numbers[3], numbers[2]),
+                                size: Vec2::new(numbers[1], numbers[0]),
+                            },
+                        )),
+                        _ => return Err(bad_line(line_index, line)),
+                    }
+                }
+                Ok(AtlasManifest::Rects { entries })
+            }
+            _ => Err(bad_line(header_index, header)),
+        }
+    }
+}
+
+/// a packed atlas image and where each source image ended up
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    pub manifest: AtlasManifest,
+}
+
+impl TextureAtlas {
+    ///
+    /// reads every png / jpeg in a folder, sorted by file name.
+    /// images are named by their file name without the extension
+    ///
+    pub fn load_folder(folder: &str) -> Result<Vec<(String, RgbaImage)>, AtlasError> {
+        let read_error = |e| AtlasError::Io(PathBuf::from(folder), e);
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(folder)
+            .map_err(read_error)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| matches!(e.to_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+            })
+            .collect();
+        paths.sort();
+
+        let mut images = Vec::with_capacity(paths.len());
+        for path in paths {
+            let image = image::open(&path).map_err(|e| AtlasError::Image(path.clone(), e))?;
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            images.push((name, image.to_rgba8()));
+        }
+        Ok(images)
+    }
+
+    ///
+    /// packs equal size images into the smallest square grid that holds them all.
+    /// images go left to right, top to bottom, so their texture_index is their
+    /// position in the list
+    ///
+    pub fn pack_grid(images: &[(String, RgbaImage)]) -> Result<TextureAtlas, AtlasError> {
+        let (_, first) = images.first().ok_or(AtlasError::Empty)?;
+        let cell = first.dimensions();
+        for (name, image) in images {
+            if image.dimensions() != cell {
+                return Err(AtlasError::SizeMismatch {
+                    name: name.clone(),
+                    expected: cell,
+                    found: image.dimensions(),
+                });
+            }
+        }
+
+        let number_of_rows = (images.len() as f64).sqrt().ceil() as u32;
+        let mut atlas = RgbaImage::new(cell.0 * number_of_rows, cell.1 * number_of_rows);
+        let mut entries = Vec::with_capacity(images.len());
+        for (index, (name, image)) in images.iter().enumerate() {
+            let index = index as u32;
+            let column = index % number_of_rows;
+            let row = index / number_of_rows;
+            image::imageops::replace(
+                &mut atlas,
+                image,
+                (column * cell.0) as i64,
+                (row * cell.1) as i64,
+            );
+            entries.push((name.clone(), index));
+        }
+
+        Ok(TextureAtlas {
+            image: atlas,
+            manifest: AtlasManifest::Grid {
+                number_of_rows,
+                entries,
+            },
+        })
+    }
+
+    ///
+    /// packs images of any size using shelves - tallest images first, filling
+    /// rows left to right. `padding` pixels are left around every image so
+    /// filtering does not bleed between neighbours. the atlas is never wider
+    /// or taller than `max_size`, an image that does not fit is an error
+    ///
+    pub fn pack_rects(
+        images: &[(String, RgbaImage)],
+        padding: u32,
+        max_size: u32,
+    ) -> Result<TextureAtlas, AtlasError> {
+        if images.is_empty() {
+            return Err(AtlasError::Empty);
+        }
+
+        let padded =
+            |image: &RgbaImage| (image.width() + padding * 2, image.height() + padding * 2);
+        let area: u64 = images
+            .iter()
+            .map(|(_, image)| {
+                let (w, h) = padded(image);
+                w as u64 * h as u64
+            })
+            .sum();
+        let widest = images
+            .iter()
+            .map(|(_, image)| padded(image).0)
+            .max()
+            .unwrap_or(1);
+        let width = ((area as f64).sqrt().ceil() as u32)
+            .max(widest)
+            .next_power_of_two()
+            .min(max_size);
+        let does_not_fit = |i: usize| AtlasError::DoesNotFit {
+            name: images[i].0.clone(),
+            max_size,
+        };
+
+        let mut order: Vec<usize> = (0..images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(images[i].1.height()));
+
+        // place everything first, then we know how tall the atlas has to be
+        let mut positions = vec![(0u32, 0u32); images.len()];
+        let (mut x, mut y, mut shelf_height) = (0u32, 0u32, 0u32);
+        for &i in &order {
+            let (w, h) = padded(&images[i].1);
+            if w > width {
+                return Err(does_not_fit(i));
+            }
+            if x + w > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            if y + h > max_size {
+                return Err(does_not_fit(i));
+            }
+            positions[i] = (x + padding, y + padding);
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        let height = (y + shelf_height).next_power_of_two().min(max_size);
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut entries = Vec::with_capacity(images.len());
+        for (i, (name, image)) in images.iter().enumerate() {
+            let (px, py) = positions[i];
+            image::imageops::replace(&mut atlas, image, px as i64, py as i64);
+            entries.push((
+                name.clone(),
+                AtlasRegion {
+                    offset: Vec2::new(px as f32 / width as f32, py as f32 / height as f32),
+                    size: Vec2::new(
+                        image.width() as f32 / width as f32,
+                        image.height() as f32 / height as f32,
+                    ),
+                },
+            ));
+        }
+
+        Ok(TextureAtlas {
+            image: atlas,
+            manifest: AtlasManifest::Rects { entries },
+        })
+    }
+
+    /// writes the atlas as a png and its manifest next to it
+    pub fn save(&self, image_path: &Path, manifest_path: &Path) -> Result<(), AtlasError> {
+        self.image
+            .save_with_format(image_path, image::ImageFormat::Png)
+            .map_err(|e| AtlasError::Image(image_path.to_path_buf(), e))?;
+        std::fs::write(manifest_path, self.manifest.to_text())
+            .map_err(|e| AtlasError::Io(manifest_path.to_path_buf(), e))
+    }
+}
@@ -0,0 +1,315 @@
+///
+/// TextureData - a decoded image ready to be uploaded by the Loader
+///
+/// decoding is kept apart from the gpu upload so it can be done (and tested)
+/// without a device
+///
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum TextureError {
+    /// the image file could not be read
+    Io(PathBuf, std::io::Error),
+    /// the data is not an image in a format we were built with
+    Image(image::ImageError),
+    /// the image is bigger than the device allows
+    TooLarge { width: u32, height: u32, max: u32 },
+    /// there were no mip levels to upload
+    NoLevels,
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            TextureError::Image(e) => write!(f, "can not decode image ({})", e),
+            TextureError::TooLarge { width, height, max } => write!(
+                f,
+                "texture is {}x{}, the device allows at most {}x{}",
+                width, height, max, max
+            ),
+            TextureError::NoLevels => write!(f, "texture has no mip levels to upload"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io(_, e) => Some(e),
+            TextureError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Image(e)
+    }
+}
+
+/// how a texture is sampled. the Loader keeps one sampler per distinct
+/// description and shares it between textures
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    /// blending between mip levels, only matters when mipmaps are generated
+    pub mipmap_filter: wgpu::MipmapFilterMode,
+    /// maximum anisotropy, 1 turns anisotropic filtering off.
+    /// ignored unless every filter is linear
+    pub anisotropy: u16,
+    /// colour outside the texture when an address mode is ClampToBorder.
+    /// ClampToBorder needs the ADDRESS_MODE_CLAMP_TO_BORDER device feature,
+    /// without it ClampToEdge is used instead
+    pub border_colour: Option<wgpu::SamplerBorderColor>,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Linear,
+            anisotropy: 1,
+            border_colour: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// tiles in both directions, for terrain, floors and the like
+    pub fn repeat() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            ..Default::default()
+        }
+    }
+
+    /// nearest filtering so every texel stays a sharp square
+    pub fn pixel_art() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    /// the wgpu descriptor for this sampler, with the settings the device
+    /// would reject corrected
+    pub fn to_descriptor(self, features: wgpu::Features) -> wgpu::SamplerDescriptor<'static> {
+        let clamp_to_border = features.contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER);
+        let address_mode = |mode: wgpu::AddressMode| {
+            if mode == wgpu::AddressMode::ClampToBorder && !clamp_to_border {
+                wgpu::AddressMode::ClampToEdge
+            } else {
+                mode
+            }
+        };
+        let all_linear = self.mag_filter == wgpu::FilterMode::Linear
+            && self.min_filter == wgpu::FilterMode::Linear
+            && self.mipmap_filter == wgpu::MipmapFilterMode::Linear;
+
+        wgpu::SamplerDescriptor {
+            label: Some("texture_sampler"),
+            address_mode_u: address_mode(self.address_mode_u),
+            address_mode_v: address_mode(self.address_mode_v),
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: if all_linear {
+                self.anisotropy.clamp(1, 16)
+            } else {
+                1
+            },
+            border_color: self.border_colour,
+            ..Default::default()
+        }
+    }
+}
+
+/// how a texture is uploaded
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureOptions {
+    /// colour textures are srgb, data like normal maps must be linear
+    pub srgb: bool,
+    /// build the full mip chain so it can be sampled trilinearly
+    pub generate_mipmaps: bool,
+    pub sampler: SamplerDesc,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            generate_mipmaps: true,
+            sampler: SamplerDesc::default(),
+        }
+    }
+}
+
+impl TextureOptions {
+    /// options for non colour data such as normal maps
+    pub fn linear() -> Self {
+        Self {
+            srgb: false,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// options for grid atlases. smaller mip levels average neighbouring
+    /// cells together, so sampling one cell would bleed in its neighbours
+    ///
+    pub fn atlas() -> Self {
+        Self {
+            generate_mipmaps: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+/// tightly packed 8 bit rgba pixels, rows top to bottom
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl TextureData {
+    /// reads and decodes an image file, the format is worked out from its contents
+    pub fn from_path(path: &str) -> Result<TextureData, TextureError> {
+        let bytes = std::fs::read(path).map_err(|e| TextureError::Io(PathBuf::from(path), e))?;
+        TextureData::from_bytes(&bytes)
+    }
+
+    /// decodes an image held in memory, the format is worked out from its contents
+    pub fn from_bytes(bytes: &[u8]) -> Result<TextureData, TextureError> {
+        let image = image::load_from_memory(bytes)?;
+        Ok(TextureData::from_image(&image))
+    }
+
+    /// converts any image (greyscale, rgb, 16 bit ...) into 8 bit rgba
+    pub fn from_image(image: &image::DynamicImage) -> TextureData {
+        let rgba = image.to_rgba8();
+        TextureData {
+            width: rgba.width(),
+            height: rgba.height(),
+            pixels: rgba.into_raw(),
+        }
+    }
+
+    /// number of levels in a full mip chain, down to 1x1
+    pub fn mip_level_count(&self) -> u32 {
+        32 - self.width.max(self.height).max(1).leading_zeros()
+    }
+
+    ///
+    /// halves the image with a 2x2 box filter. odd sizes round down and the
+    /// left over last row / column is averaged into the texels next to it, so
+    /// every source texel counts. srgb data is averaged in linear space so
+    /// smaller levels do not get darker, alpha is always linear
+    ///
+    pub fn downsample(&self, srgb: bool) -> TextureData {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let to_linear = |value: u8| {
+            let value = value as f32 / 255.0;
+            if !srgb {
+                value
+            } else if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let from_linear = |value: f32| {
+            let value = if !srgb {
+                value
+            } else if value <= 0.0031308 {
+                value * 12.92
+            } else {
+                1.055 * value.powf(1.0 / 2.4) - 0.055
+            };
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+
+        // the source texels one texel covers, the last one also takes the
+        // odd row / column left over
+        let span = |i: u32, size: u32, source_size: u32| {
+            let end = if i + 1 == size {
+                source_size
+            } else {
+                i * 2 + 2
+            };
+            i * 2..end.min(source_size).max(i * 2 + 1)
+        };
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0f32; 4];
+                let mut count = 0.0;
+                for source_y in span(y, height, self.height) {
+                    for source_x in span(x, width, self.width) {
+                        let offset = ((source_y * self.width + source_x) * 4) as usize;
+                        for (channel, total) in sum.iter_mut().take(3).enumerate() {
+                            *total += to_linear(self.pixels[offset + channel]);
+                        }
+                        sum[3] += self.pixels[offset + 3] as f32 / 255.0;
+                        count += 1.0;
+                    }
+                }
+                for total in sum.iter().take(3) {
+                    pixels.push(from_linear(total / count));
+                }
+                pixels.push((sum[3] / count * 255.0).round() as u8);
+            }
+        }
+
+        TextureData {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// every level of the mip chain, starting with a copy of this image
+    pub fn generate_mipmaps(&self, srgb: bool) -> Vec<TextureData> {
+        let mut levels = vec![self.clone()];
+        for _ in 1..self.mip_level_count() {
+            let next = levels[levels.len() - 1].downsample(srgb);
+            levels.push(next);
+        }
+        levels
+    }
+
+    /// single pixel texture of a 0..1 rgba colour, used for untextured materials
+    pub fn solid_colour(colour: glam::Vec4) -> TextureData {
+        let colour = (colour.clamp(glam::Vec4::ZERO, glam::Vec4::ONE) * 255.0)
+            .round()
+            .as_u8vec4();
+        TextureData {
+            width: 1,
+            height: 1,
+            pixels: colour.to_array().to_vec(),
+        }
+    }
+}
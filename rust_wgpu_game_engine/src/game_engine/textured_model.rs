@@ -1,7 +1,8 @@
 use super::{model_texture::ModelTexture, raw_model::RawModel};
 use std::hash::Hash;
 
-/// Textured model combines a texture and a 3d model
+/// Textured model combines a texture and a 3d model.
+/// two are the same batch when both the mesh and the texture are the same
 #[derive(Clone)]
 pub struct TexturedModel {
     pub model: RawModel,
@@ -10,7 +11,7 @@ pub struct TexturedModel {
 
 impl Hash for TexturedModel {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        (self.texture.id + self.texture.number_of_rows).hash(state)
+        (self.model.id, self.texture.id).hash(state)
     }
 }
 
@@ -18,7 +19,7 @@ impl std::cmp::Eq for TexturedModel {}
 
 impl PartialEq for TexturedModel {
     fn eq(&self, other: &Self) -> bool {
-        self.texture.id == other.texture.id
+        self.model.id == other.model.id && self.texture.id == other.texture.id
     }
 }
 
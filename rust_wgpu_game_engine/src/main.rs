@@ -1,10 +1,19 @@
 use rust_wgpu_game_engine::game_engine::{
+    asset_manager::{AssetManager, Handle, PendingModel, Reloaded},
+    assets::{self, models, textures},
     camera::Camera, // Added
     entity::Entity, // Import your entity
-    light::Light,   // Added
+    hot_reload::HotReloader,
+    light::Light, // Added
     loader,
     main_shader,
     master_renderer::MasterRenderer, // Added
+    model_texture::ModelTexture,
+    raw_model::RawModel,
+    renderer::Renderer,
+    terrain::Terrain,
+    terrain_texture::{TerrainTexture, TerrainTexturePack},
+    texture_data::{TextureError, TextureOptions},
     textured_model::TexturedModel,
 };
 use std::sync::Arc;
@@ -29,8 +38,27 @@ struct State<'a> {
     camera: Camera,
     sun: Light,
     entities: Vec<Entity>, // Removed duplicate declaration
+    terrains: Vec<Terrain>,
+    assets: AssetManager,
+    // only in debug builds, watches res/ and the shader for changes
+    hot_reloader: Option<HotReloader>,
+    // held so the manager keeps these loaded and can hot reload them
+    _cube_model: Handle<RawModel>,
+    _cube_texture: Handle<ModelTexture>,
+    // built in shape drawn instead of assets that failed to load
+    fallback: TexturedModel,
 }
 
+/// gentle rolling hills, flat enough at the origin for the cube to sit on
+fn hills(x: f32, z: f32) -> f32 {
+    4.0 * (x * 0.05).sin() * (z * 0.04).cos() + 2.0 * ((x + z) * 0.013).sin()
+}
+
+// the shader source in the repo rather than the copy built into the binary,
+// whatever the working directory. hot reload is only on in debug builds
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl");
+const TERRAIN_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/terrain_shader.wgsl");
+
 impl<'a> State<'a> {
     async fn new(window: Arc<Window>) -> State<'a> {
         let size = window.inner_size();
@@ -106,45 +134,58 @@ impl<'a> State<'a> {
             label: Some("texture_bind_group_layout"),
         });
 
-        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: Some("uniform_layout"),
-        });
+        let uniform_layout = Renderer::create_uniform_layout(&device);
 
-        let mut my_loader = loader::Loader::new(&device, queue.clone());
+        let mut assets = AssetManager::new(loader::Loader::new(&device, queue.clone()));
+        // the depth buffer has to match the lens, reverse-Z tests the other way round
+        let mut camera = Camera::new();
+        camera.projection.set_viewport(config.width, config.height);
+        let depth = camera.projection.depth_settings();
         let my_shader =
-            main_shader::MainShader::new(&device, &config, &texture_layout, &uniform_layout);
-        let my_master_renderer = MasterRenderer::new(&device, &uniform_layout); //
+            main_shader::MainShader::new(&device, &config, &texture_layout, &uniform_layout, depth);
+        let my_master_renderer = MasterRenderer::new(&device, &config, &uniform_layout, depth); //
+
+        // without its textures there is no ground, everything else still works
+        let terrains = State::load_terrains(&mut assets).unwrap_or_else(|e| {
+            log::error!("can not load the terrain: {}", e);
+            Vec::new()
+        });
 
-        let mut camera = Camera::new();
         camera.position.z = 10.0; // Move the camera back 5 units
+        camera.position.y = State::ground_height(&terrains, 0.0, 10.0) + 3.0;
 
-        let sun = Light::new();
+        // high up and off to one side, so the faces of a cube are lit differently
+        let mut sun = Light::new();
+        sun.position = glam::Vec3::new(300.0, 500.0, 200.0);
 
-        let raw_model = my_loader.load_3d_model("res/cube.obj");
-        let mut texture = my_loader.load_texture();
-        texture.number_of_rows = 8;
+        // a broken asset should not bring the whole game down, fall back to the built in shape
+        let mut fallback_texture = assets.get_loader().load_texture();
+        fallback_texture.number_of_rows = 8;
+        let fallback = TexturedModel::new(&assets.get_loader().triangle(), &fallback_texture);
 
-        let textured_model = TexturedModel::new(&raw_model, &texture);
+        // the mesh is embedded in the binary, the texture is loaded in the
+        // background and the cube appears once it has been uploaded
+        let cube_model = assets.load_model_asset(&models::CUBE).unwrap_or_else(|e| {
+            log::error!("{}: {}", models::CUBE.path, e);
+            assets.load_model_async(&models::CUBE.full_path())
+        });
+        // the maze is an 8 row atlas, mip levels would blur its cells together
+        let cube_texture =
+            assets.load_texture_async(&textures::MAZE.full_path(), &TextureOptions::atlas());
+        let mut pending = PendingModel::new(&cube_model, &cube_texture);
+        pending.number_of_rows = 8;
 
-        let entity = Entity::new(
-            textured_model,
-            glam::Vec3::new(0.0, 0.0, 0.0),
+        let mut entity = Entity::new(
+            assets.get_placeholder(),
+            // standing on the ground, the cube is 2 units across
+            glam::Vec3::new(0.0, State::ground_height(&terrains, 0.0, 0.0) + 1.0, 0.0),
             0.0,
             0.0,
             0.0,
             1.0,
             2,
         );
+        entity.set_pending(Some(pending));
 
         Self {
             window,
@@ -158,6 +199,142 @@ impl<'a> State<'a> {
             camera,
             sun,
             entities: vec![entity],
+            terrains,
+            assets,
+            hot_reloader: State::start_hot_reloader(),
+            _cube_model: cube_model,
+            _cube_texture: cube_texture,
+            fallback,
+        }
+    }
+
+    /// four terrains around the origin, grass with mud, flowers and a path blended in
+    fn load_terrains(assets: &mut AssetManager) -> Result<Vec<Terrain>, TextureError> {
+        let pack = TerrainTexturePack::load(
+            assets,
+            &textures::TERRAIN_GRASS.full_path(),
+            &textures::TERRAIN_MUD.full_path(),
+            &textures::TERRAIN_FLOWERS.full_path(),
+            &textures::TERRAIN_PATH.full_path(),
+        )?;
+        let texture = TerrainTexture::new(assets, &pack, &textures::TERRAIN_BLEND_MAP.full_path())?;
+        let loader = assets.get_loader();
+        Ok([(-1, -1), (0, -1), (-1, 0), (0, 0)]
+            .into_iter()
+            .map(|(grid_x, grid_z)| {
+                Terrain::from_height_fn(grid_x, grid_z, loader, &texture, 128, hills)
+            })
+            .collect())
+    }
+
+    /// height of whichever terrain is under a point, 0 off the edge of the world
+    fn ground_height(terrains: &[Terrain], x: f32, z: f32) -> f32 {
+        terrains
+            .iter()
+            .find(|terrain| terrain.contains(x, z))
+            .map_or(0.0, |terrain| terrain.get_height_of_terrain(x, z))
+    }
+
+    fn start_hot_reloader() -> Option<HotReloader> {
+        if !cfg!(debug_assertions) {
+            return None;
+        }
+        let mut reloader = HotReloader::new()
+            .inspect_err(|e| log::warn!("hot reloading is off: {}", e))
+            .ok()?;
+        let res_path = assets::resolve("res");
+        for path in [res_path.as_str(), SHADER_PATH, TERRAIN_SHADER_PATH] {
+            if let Err(e) = reloader.watch(path) {
+                log::warn!("can not watch {} for changes: {}", path, e);
+            }
+        }
+        Some(reloader)
+    }
+
+    /// uploads finished background loads and shows progress in the title bar
+    fn update_loading(&mut self) {
+        self.assets.update();
+        for entity in &mut self.entities {
+            let Some(pending) = &entity.pending else {
+                continue;
+            };
+            // keep whichever half did load, use the built in one for the other
+            if pending.model.is_failed() || pending.texture.is_failed() {
+                let model = pending
+                    .model
+                    .get()
+                    .map_or(self.fallback.model.clone(), |model| (*model).clone());
+                let texture =
+                    pending
+                        .texture
+                        .get()
+                        .map_or(self.fallback.texture.clone(), |texture| {
+                            let mut texture = (*texture).clone();
+                            texture.number_of_rows = pending.number_of_rows;
+                            texture
+                        });
+                entity.model = TexturedModel::new(&model, &texture);
+                entity.pending = None;
+            } else {
+                entity.update_pending();
+            }
+        }
+
+        let progress = self.assets.get_progress();
+        let title = if progress.is_done() {
+            "Rust Game Engine".to_string()
+        } else {
+            format!(
+                "Rust Game Engine - loading {:.0}%",
+                progress.fraction() * 100.0
+            )
+        };
+        if self.window.title() != title {
+            self.window.set_title(&title);
+        }
+    }
+
+    /// rebuilds anything that changed on disk, before the frame is drawn
+    fn reload_changed_assets(&mut self) {
+        let Some(reloader) = self.hot_reloader.as_mut() else {
+            return;
+        };
+        for path in reloader.poll_changes() {
+            if path.extension().is_some_and(|e| e == "wgsl") {
+                let source = path.to_string_lossy();
+                let result = if path.ends_with("terrain_shader.wgsl") {
+                    self.my_master_renderer.reload_terrain_shader(&source)
+                } else {
+                    self.my_shader.reload(&self.device, &source)
+                };
+                match result {
+                    Ok(()) => log::info!("reloaded {}", path.display()),
+                    // keep drawing with the pipeline we already have
+                    Err(e) => log::error!("{}", e),
+                }
+                continue;
+            }
+            match self.assets.reload(&path) {
+                Ok(reloaded) => {
+                    for change in &reloaded {
+                        for entity in &mut self.entities {
+                            change.apply(entity);
+                        }
+                        // the terrain binds its textures together, so bind them again
+                        if let Reloaded::Texture { old, .. } = change {
+                            for terrain in &mut self.terrains {
+                                if terrain.texture.uses(old.id) {
+                                    terrain.texture.rebuild(self.assets.get_loader());
+                                }
+                            }
+                        }
+                    }
+                    if !reloaded.is_empty() {
+                        log::info!("reloaded {}", path.display());
+                    }
+                }
+                Err(e) => log::error!("{}", e),
+            }
         }
     }
 
@@ -167,15 +344,29 @@ impl<'a> State<'a> {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.camera
+                .projection
+                .set_viewport(new_size.width, new_size.height);
+            self.my_master_renderer
+                .resize(&self.device, new_size.width, new_size.height);
         }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.update_loading();
+        self.reload_changed_assets();
+
         // 1. Prepare MasterRenderer
         self.my_master_renderer.clear_entities();
+        self.my_master_renderer.clear_terrains();
+        self.my_master_renderer
+            .update_lods(&self.camera, &mut self.entities);
         for entity in &self.entities {
             self.my_master_renderer.add_entity(entity.clone());
         }
+        for terrain in &self.terrains {
+            self.my_master_renderer.add_terrain(terrain.clone());
+        }
 
         let output = self.surface.get_current_texture()?;
         let view = output
@@ -204,13 +395,18 @@ impl<'a> State<'a> {
                     },
                     depth_slice: None,
                 })],
+                depth_stencil_attachment: Some(self.my_master_renderer.depth_stencil_attachment()),
                 ..Default::default()
             });
 
             // 2. Execute Batch Rendering
-            render_pass.set_pipeline(&self.my_shader.render_pipeline);
-            self.my_master_renderer
-                .render(&mut render_pass, &self.queue, &self.sun, &self.camera);
+            self.my_master_renderer.render(
+                &mut render_pass,
+                &self.queue,
+                &self.my_shader,
+                &self.sun,
+                &self.camera,
+            );
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -4,9 +4,23 @@ struct VertexInput {
     @location(2) normals: vec3<f32>,
 };
 
+// one per entity, see EntityInstance in renderer.rs
+struct InstanceInput {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+    @location(7) tex_offset: vec2<f32>,
+    @location(8) tex_scale: vec2<f32>,
+    @location(9) tint: vec4<f32>,
+};
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
 };
 
 // Group 0: Texture and Sampler
@@ -15,29 +29,71 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
-// 1. Updated Uniform struct to match renderer.rs
-struct EntityUniforms {
-    transform_matrix: mat4x4<f32>,
-    tex_offset: vec2<f32>,
-    num_rows: f32,
+// 1. Uniform struct to match CameraUniforms in renderer.rs, the same for every entity
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
 };
 
 @group(1) @binding(0)
-var<uniform> uniforms: EntityUniforms;
+var<uniform> camera: CameraUniforms;
+
+// Group 2: matches LightingUniforms in light.rs, one per textured model
+struct Light {
+    position: vec3<f32>,
+    colour: vec3<f32>,
+};
+
+struct Lighting {
+    sun: Light,
+    camera_position: vec3<f32>,
+    shine_damper: f32,
+    reflectivity: f32,
+    ambient: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> lighting: Lighting;
 
 @vertex
-fn vs_main(model: VertexInput) -> VertexOutput {
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
     var out: VertexOutput;
     
     // 2. Calculate Atlas UV coordinates:
-    // Shrink the UV to the size of one tile, then add the entity's offset
-    out.tex_coords = (model.tex_coords / uniforms.num_rows) + uniforms.tex_offset;
+    // Shrink the UV to the size of one tile (or atlas region), then add the entity's offset
+    out.tex_coords = (model.tex_coords * instance.tex_scale) + instance.tex_offset;
+    out.tint = instance.tint;
     
-    out.clip_position = uniforms.transform_matrix * vec4<f32>(model.position, 1.0);
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    // w of 0 leaves out the translation, entities are only scaled uniformly
+    out.world_normal = (model_matrix * vec4<f32>(model.normals, 0.0)).xyz;
+    out.clip_position = camera.view_projection * world_position;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
-}
\ No newline at end of file
+    let colour = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.tint;
+
+    let unit_normal = normalize(in.world_normal);
+    let to_light = normalize(lighting.sun.position - in.world_position);
+    let to_camera = normalize(lighting.camera_position - in.world_position);
+
+    // diffuse, never darker than the ambient floor
+    let n_dot_l = dot(unit_normal, to_light);
+    let brightness = max(n_dot_l, lighting.ambient);
+    let diffuse = brightness * lighting.sun.colour;
+
+    // Blinn-Phong specular, only on the side facing the light
+    let halfway = normalize(to_light + to_camera);
+    let specular_factor = pow(max(dot(unit_normal, halfway), 0.0), lighting.shine_damper);
+    let specular = select(0.0, specular_factor, n_dot_l > 0.0) * lighting.reflectivity * lighting.sun.colour;
+
+    return vec4<f32>(colour.rgb * diffuse + specular, colour.a);
+}
@@ -0,0 +1,92 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normals: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // 0 to 1 across the terrain, for the blend map
+    @location(0) tex_coords: vec2<f32>,
+    // repeats `tiling` times across the terrain, for the four ground textures
+    @location(1) tiled_coords: vec2<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
+};
+
+// Group 0: the texture pack, the blend map and their samplers
+@group(0) @binding(0)
+var t_background: texture_2d<f32>;
+@group(0) @binding(1)
+var t_r: texture_2d<f32>;
+@group(0) @binding(2)
+var t_g: texture_2d<f32>;
+@group(0) @binding(3)
+var t_b: texture_2d<f32>;
+@group(0) @binding(4)
+var t_blend_map: texture_2d<f32>;
+@group(0) @binding(5)
+var s_tiling: sampler;
+@group(0) @binding(6)
+var s_blend_map: sampler;
+
+// same layout as TerrainUniforms in terrain_renderer.rs, tex_scale holds the tiling
+struct TerrainUniforms {
+    transform_matrix: mat4x4<f32>,
+    // where the terrain is in the world, for the lighting
+    model_matrix: mat4x4<f32>,
+    tex_offset: vec2<f32>,
+    tex_scale: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> uniforms: TerrainUniforms;
+
+// Group 2: the same lighting as shader.wgsl, LightingUniforms in light.rs
+struct Light {
+    position: vec3<f32>,
+    colour: vec3<f32>,
+};
+
+struct Lighting {
+    sun: Light,
+    camera_position: vec3<f32>,
+    shine_damper: f32,
+    reflectivity: f32,
+    ambient: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> lighting: Lighting;
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.tiled_coords = model.tex_coords * uniforms.tex_scale + uniforms.tex_offset;
+    out.world_position = (uniforms.model_matrix * vec4<f32>(model.position, 1.0)).xyz;
+    // terrains are only moved, never rotated or scaled
+    out.world_normal = (uniforms.model_matrix * vec4<f32>(model.normals, 0.0)).xyz;
+    out.clip_position = uniforms.transform_matrix * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let blend = textureSample(t_blend_map, s_blend_map, in.tex_coords);
+    // whatever the three channels do not cover shows the background
+    let background_amount = clamp(1.0 - (blend.r + blend.g + blend.b), 0.0, 1.0);
+
+    let background = textureSample(t_background, s_tiling, in.tiled_coords) * background_amount;
+    let r = textureSample(t_r, s_tiling, in.tiled_coords) * blend.r;
+    let g = textureSample(t_g, s_tiling, in.tiled_coords) * blend.g;
+    let b = textureSample(t_b, s_tiling, in.tiled_coords) * blend.b;
+
+    // diffuse only, never darker than the ambient floor. the ground is not shiny
+    let unit_normal = normalize(in.world_normal);
+    let to_light = normalize(lighting.sun.position - in.world_position);
+    let brightness = max(dot(unit_normal, to_light), lighting.ambient);
+    let diffuse = brightness * lighting.sun.colour;
+
+    return vec4<f32>((background + r + g + b).rgb * diffuse, 1.0);
+}
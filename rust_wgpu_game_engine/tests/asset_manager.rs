@@ -0,0 +1,108 @@
+///
+/// the asset cache without a gpu - one handle per path, and assets forgotten
+/// once nothing holds them
+///
+use std::{convert::Infallible, path::PathBuf, sync::Arc};
+
+use rust_wgpu_game_engine::game_engine::asset_manager::{AssetCache, AssetManager, Handle, Upload};
+
+/// stands in for an upload, clones share `users` like RawModel does
+#[derive(Clone)]
+struct FakeAsset {
+    users: Arc<()>,
+}
+
+impl Upload for FakeAsset {
+    fn users(&self) -> &Arc<()> {
+        &self.users
+    }
+}
+
+fn load(
+    cache: &mut AssetCache<PathBuf, FakeAsset>,
+    path: &str,
+    loads: &mut u32,
+) -> Handle<FakeAsset> {
+    let key = AssetManager::normalise_path(path);
+    cache
+        .get_or_load(key.clone(), &key, || {
+            *loads += 1;
+            Ok::<_, Infallible>(FakeAsset {
+                users: Arc::new(()),
+            })
+        })
+        .unwrap()
+}
+
+#[test]
+fn same_path_gives_the_same_handle() {
+    let mut cache = AssetCache::new();
+    let mut loads = 0;
+    let first = load(&mut cache, "res/models/dragon.obj", &mut loads);
+    let second = load(&mut cache, "res/models/dragon.obj", &mut loads);
+    let respelled = load(&mut cache, "res/models/../models/./dragon.obj", &mut loads);
+
+    assert!(first.ptr_eq(&second));
+    assert!(first.ptr_eq(&respelled));
+    assert_eq!(loads, 1);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn different_paths_give_different_handles() {
+    let mut cache = AssetCache::new();
+    let mut loads = 0;
+    let dragon = load(&mut cache, "res/models/dragon.obj", &mut loads);
+    let stall = load(&mut cache, "res/models/stall.obj", &mut loads);
+
+    assert!(!dragon.ptr_eq(&stall));
+    assert_eq!(loads, 2);
+}
+
+#[test]
+fn released_once_the_handles_drop() {
+    let mut cache = AssetCache::new();
+    let mut loads = 0;
+    let first = load(&mut cache, "res/models/dragon.obj", &mut loads);
+    let second = first.clone();
+
+    drop(first);
+    cache.remove_unused();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.in_use_count(), 1);
+
+    drop(second);
+    assert_eq!(cache.in_use_count(), 0);
+    cache.remove_unused();
+    assert!(cache.is_empty());
+
+    // loading it again after the release reads the file again
+    load(&mut cache, "res/models/dragon.obj", &mut loads);
+    assert_eq!(loads, 2);
+}
+
+#[test]
+fn a_copy_of_the_asset_keeps_it_cached() {
+    let mut cache = AssetCache::new();
+    let mut loads = 0;
+    let handle = load(&mut cache, "res/models/dragon.obj", &mut loads);
+    let copy = FakeAsset::clone(&handle.get().unwrap());
+    drop(handle);
+
+    cache.remove_unused();
+    assert_eq!(cache.len(), 1);
+
+    drop(copy);
+    cache.remove_unused();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn failed_loads_are_not_cached() {
+    let mut cache: AssetCache<PathBuf, FakeAsset> = AssetCache::new();
+    let key = PathBuf::from("res/models/missing.obj");
+    let result = cache.get_or_load(key.clone(), &key, || Err("not found"));
+
+    assert!(result.is_err());
+    assert!(cache.get(&key).is_none());
+}
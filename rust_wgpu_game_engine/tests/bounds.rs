@@ -0,0 +1,158 @@
+///
+/// boxes and spheres around points, and how they follow an entity's
+/// transform
+///
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use glam::{Mat4, Quat, Vec3};
+use rust_wgpu_game_engine::game_engine::bounds::{Aabb, BoundingSphere};
+
+fn assert_near(a: Vec3, b: Vec3) {
+    assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+}
+
+/// a 2 x 4 x 6 box around (1, 2, 3)
+fn test_box() -> Aabb {
+    Aabb {
+        min: Vec3::new(0.0, 0.0, 0.0),
+        max: Vec3::new(2.0, 4.0, 6.0),
+    }
+}
+
+#[test]
+fn a_box_fits_its_points() {
+    let points = [
+        Vec3::new(1.0, -2.0, 0.5),
+        Vec3::new(-3.0, 4.0, 0.0),
+        Vec3::new(0.0, 0.0, 7.0),
+    ];
+    let aabb = Aabb::from_points(points);
+
+    assert_eq!(aabb.min, Vec3::new(-3.0, -2.0, 0.0));
+    assert_eq!(aabb.max, Vec3::new(1.0, 4.0, 7.0));
+    assert!(points.iter().all(|&p| aabb.contains(p)));
+    assert_eq!(aabb.center(), Vec3::new(-1.0, 1.0, 3.5));
+    assert_eq!(aabb.extents(), Vec3::new(2.0, 3.0, 3.5));
+}
+
+#[test]
+fn no_points_give_an_empty_box_at_the_origin() {
+    let aabb = Aabb::from_points(std::iter::empty());
+
+    assert_eq!(aabb, Aabb::default());
+    let single = Aabb::from_points([Vec3::ONE]);
+    assert_eq!((single.min, single.max), (Vec3::ONE, Vec3::ONE));
+}
+
+#[test]
+fn translating_a_box_moves_it_without_changing_its_size() {
+    let moved = test_box().transform(&Mat4::from_translation(Vec3::new(10.0, -5.0, 1.0)));
+
+    assert_near(moved.min, Vec3::new(10.0, -5.0, 1.0));
+    assert_near(moved.max, Vec3::new(12.0, -1.0, 7.0));
+}
+
+#[test]
+fn a_rotated_box_grows_to_hold_its_corners() {
+    let cube = Aabb {
+        min: Vec3::splat(-1.0),
+        max: Vec3::splat(1.0),
+    };
+    let rotation = Mat4::from_rotation_y(FRAC_PI_4);
+    let rotated = cube.transform(&rotation);
+
+    // the corners swing out to sqrt(2) along x and z, y is untouched
+    let reach = 2.0f32.sqrt();
+    assert_near(rotated.min, Vec3::new(-reach, -1.0, -reach));
+    assert_near(rotated.max, Vec3::new(reach, 1.0, reach));
+
+    // every rotated corner is still inside
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                let corner = rotation.transform_point3(Vec3::new(x, y, z));
+                assert!(
+                    rotated.contains(corner * (1.0 - 1e-5)),
+                    "{} is outside",
+                    corner
+                );
+            }
+        }
+    }
+
+    // a quarter turn swaps the sides of the box rather than growing it
+    let turned = test_box().transform(&Mat4::from_rotation_z(FRAC_PI_2));
+    assert_near(turned.extents(), Vec3::new(2.0, 1.0, 3.0));
+}
+
+#[test]
+fn scaling_and_moving_a_box_together() {
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::new(2.0, 0.5, 1.0),
+        Quat::IDENTITY,
+        Vec3::new(0.0, 10.0, 0.0),
+    );
+    let moved = test_box().transform(&matrix);
+
+    assert_near(moved.min, Vec3::new(0.0, 10.0, 0.0));
+    assert_near(moved.max, Vec3::new(4.0, 12.0, 6.0));
+}
+
+#[test]
+fn a_sphere_holds_its_points() {
+    let points = [
+        Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+    ];
+    let sphere = BoundingSphere::from_points(points);
+
+    // centred on the box around the points
+    assert_near(sphere.center, Vec3::new(1.0, 0.5, 0.0));
+    assert!(points.iter().all(|&p| sphere.contains(p)));
+    let furthest = points
+        .iter()
+        .map(|p| p.distance(sphere.center))
+        .fold(0.0, f32::max);
+    assert!((sphere.radius - furthest).abs() < 1e-6);
+}
+
+#[test]
+fn translating_a_sphere_moves_only_its_centre() {
+    let sphere = BoundingSphere {
+        center: Vec3::new(1.0, 2.0, 3.0),
+        radius: 2.0,
+    };
+    let moved = sphere.transform(&Mat4::from_translation(Vec3::new(-1.0, 0.0, 5.0)));
+
+    assert_near(moved.center, Vec3::new(0.0, 2.0, 8.0));
+    assert_eq!(moved.radius, 2.0);
+
+    let rotated = sphere.transform(&Mat4::from_rotation_x(1.0));
+    assert!((rotated.radius - 2.0).abs() < 1e-5);
+}
+
+#[test]
+fn a_sphere_scales_by_its_largest_axis() {
+    let sphere = BoundingSphere {
+        center: Vec3::new(1.0, 0.0, 0.0),
+        radius: 1.5,
+    };
+    for scale in [
+        Vec3::new(3.0, 1.0, 0.5),
+        Vec3::new(1.0, 3.0, 0.5),
+        Vec3::new(0.5, 1.0, 3.0),
+    ] {
+        let matrix =
+            Mat4::from_scale_rotation_translation(scale, Quat::from_rotation_y(0.7), Vec3::ZERO);
+        let scaled = sphere.transform(&matrix);
+
+        assert!(
+            (scaled.radius - 4.5).abs() < 1e-5,
+            "{} for {}",
+            scaled.radius,
+            scale
+        );
+        assert_near(scaled.center, matrix.transform_point3(sphere.center));
+    }
+}
@@ -0,0 +1,316 @@
+///
+/// decodes small gltf documents built in memory, with their buffers as data
+/// uris or files in the temp folder, and checks what GltfScene makes of them.
+/// nothing here needs a gpu
+///
+use std::io::Cursor;
+
+use base64::Engine;
+use glam::{Mat4, Vec3};
+use rust_wgpu_game_engine::game_engine::gltf_loader::{GltfError, GltfScene};
+
+const POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+fn data_uri(mime: &str, bytes: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// the positions of a triangle, then its u16 indices padded out to 4 bytes
+fn triangle_buffer() -> Vec<u8> {
+    let mut buffer: Vec<u8> = bytemuck::cast_slice(&POSITIONS).to_vec();
+    buffer.extend_from_slice(bytemuck::cast_slice(&[0u16, 1, 2, 0]));
+    buffer
+}
+
+///
+/// a document with triangle_buffer at `uri`, accessor 0 its positions and
+/// accessor 1 its indices. `rest` adds the meshes, nodes and so on
+///
+fn triangle_document(uri: &str, rest: &str) -> Vec<u8> {
+    format!(
+        r#"{{
+            "asset": {{ "version": "2.0" }},
+            "buffers": [{{ "byteLength": 44, "uri": "{uri}" }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                   "min": [0, 0, 0], "max": [1, 1, 0] }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            {rest}
+        }}"#
+    )
+    .into_bytes()
+}
+
+/// one mesh of the triangle without normals, on one node
+fn triangle_scene(uri: &str) -> Vec<u8> {
+    triangle_document(
+        uri,
+        r#""meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+           "nodes": [{ "mesh": 0 }]"#,
+    )
+}
+
+/// a single triangle primitive with `normals` normals and the given indices
+fn triangle_with_normals(normals: usize, indices: [u16; 3]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = bytemuck::cast_slice(&POSITIONS).to_vec();
+    let normal_offset = buffer.len();
+    for _ in 0..normals {
+        buffer.extend_from_slice(bytemuck::cast_slice(&[0.0f32, 0.0, 1.0]));
+    }
+    let index_offset = buffer.len();
+    buffer.extend_from_slice(bytemuck::cast_slice(&indices));
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    format!(
+        r#"{{
+            "asset": {{ "version": "2.0" }},
+            "buffers": [{{ "byteLength": {length}, "uri": "{uri}" }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": {normal_offset}, "byteLength": {normal_length} }},
+                {{ "buffer": 0, "byteOffset": {index_offset}, "byteLength": 6 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                   "min": [0, 0, 0], "max": [1, 1, 0] }},
+                {{ "bufferView": 1, "componentType": 5126, "count": {normals}, "type": "VEC3" }},
+                {{ "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }}, "indices": 2 }}] }}],
+            "nodes": [{{ "mesh": 0 }}],
+            "scenes": [{{ "nodes": [0] }}]
+        }}"#,
+        length = buffer.len(),
+        uri = data_uri("application/octet-stream", &buffer),
+        normal_length = normals * 12,
+    )
+    .into_bytes()
+}
+
+/// a 2x2 png of one colour
+fn png(colour: [u8; 4]) -> Vec<u8> {
+    let image = image::RgbaImage::from_pixel(2, 2, image::Rgba(colour));
+    let mut bytes = Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(image)
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn a_triangle_is_read() {
+    let scene = GltfScene::from_slice(&triangle_with_normals(3, [0, 1, 2])).unwrap();
+
+    let data = &scene.meshes[0].primitives[0].data;
+    assert_eq!(data.vertices.len() / 3, 3);
+    assert_eq!(data.indice, [0, 1, 2]);
+    assert_eq!(&data.normal[..3], [0.0, 0.0, 1.0]);
+    assert_eq!(scene.nodes[0].mesh, Some(0));
+}
+
+#[test]
+fn a_primitive_without_normals_is_flat_shaded() {
+    let uri = data_uri("application/octet-stream", &triangle_buffer());
+    let scene = GltfScene::from_slice(&triangle_scene(&uri)).unwrap();
+
+    let data = &scene.meshes[0].primitives[0].data;
+    assert_eq!(data.vertices.len() / 3, 3);
+    for normal in data.normal.chunks_exact(3) {
+        assert_eq!(normal, [0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn an_external_buffer_is_found_through_its_escaped_uri() {
+    let folder = std::env::temp_dir().join(format!("gltf_loader_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    std::fs::write(folder.join("the triangle.bin"), triangle_buffer()).unwrap();
+    let path = folder.join("triangle.gltf");
+    std::fs::write(&path, triangle_scene("the%20triangle.bin")).unwrap();
+
+    let scene = GltfScene::from_path(&path.to_string_lossy()).unwrap();
+    assert_eq!(scene.meshes[0].primitives[0].data.indice, [0, 1, 2]);
+
+    // from memory there is no folder to look in
+    let error = GltfScene::from_slice(&triangle_scene("the%20triangle.bin"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, GltfError::Uri(_)), "{}", error);
+}
+
+#[test]
+fn a_data_uri_that_is_not_base64_is_rejected() {
+    let document = triangle_scene("data:application/octet-stream;base64,not*base64");
+    let error = GltfScene::from_slice(&document).err().unwrap();
+
+    assert!(matches!(error, GltfError::Uri(_)), "{}", error);
+}
+
+#[test]
+fn a_buffer_shorter_than_declared_is_rejected() {
+    let short = &triangle_buffer()[..20];
+    let document = triangle_scene(&data_uri("application/octet-stream", short));
+    let error = GltfScene::from_slice(&document).err().unwrap();
+
+    assert!(matches!(error, GltfError::Truncated(_)), "{}", error);
+}
+
+#[test]
+fn materials_images_and_samplers_are_read() {
+    let uri = data_uri("application/octet-stream", &triangle_buffer());
+    let image = data_uri("image/png", &png([255, 0, 0, 255]));
+    let document = triangle_document(
+        &uri,
+        &format!(
+            r#""meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}] }}],
+               "nodes": [{{ "mesh": 0 }}],
+               "images": [{{ "uri": "{image}" }}],
+               "samplers": [{{ "magFilter": 9728, "minFilter": 9984, "wrapS": 33648, "wrapT": 33071 }}],
+               "textures": [{{ "source": 0, "sampler": 0 }}],
+               "materials": [{{
+                   "name": "painted",
+                   "alphaMode": "BLEND",
+                   "pbrMetallicRoughness": {{
+                       "baseColorFactor": [1, 0.5, 0.25, 1],
+                       "baseColorTexture": {{ "index": 0 }},
+                       "metallicFactor": 0,
+                       "roughnessFactor": 1
+                   }}
+               }}]"#
+        ),
+    );
+    let scene = GltfScene::from_slice(&document).unwrap();
+
+    assert_eq!((scene.images[0].width, scene.images[0].height), (2, 2));
+    assert_eq!(&scene.images[0].pixels[..4], [255, 0, 0, 255]);
+
+    let material = &scene.materials[0];
+    assert_eq!(material.name.as_deref(), Some("painted"));
+    assert_eq!(material.base_colour.to_array(), [1.0, 0.5, 0.25, 1.0]);
+    assert_eq!(material.base_colour_texture, Some(0));
+    assert!(material.has_transparency);
+    // fully rough surfaces do not reflect, and get the widest highlight
+    assert_eq!(material.reflectivity, 0.0);
+    assert_eq!(material.shine_damper, 1.0);
+
+    let sampler = material.base_colour_sampler;
+    assert_eq!(sampler.address_mode_u, wgpu::AddressMode::MirrorRepeat);
+    assert_eq!(sampler.address_mode_v, wgpu::AddressMode::ClampToEdge);
+    assert_eq!(sampler.mag_filter, wgpu::FilterMode::Nearest);
+    assert_eq!(sampler.min_filter, wgpu::FilterMode::Nearest);
+    assert_eq!(sampler.mipmap_filter, wgpu::MipmapFilterMode::Nearest);
+    assert_eq!(scene.meshes[0].primitives[0].material, Some(0));
+}
+
+#[test]
+fn children_are_placed_relative_to_their_parents() {
+    let uri = data_uri("application/octet-stream", &triangle_buffer());
+    let document = triangle_document(
+        &uri,
+        r#""meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+           "nodes": [
+               { "name": "root", "translation": [1, 0, 0], "scale": [2, 2, 2], "children": [1] },
+               { "name": "child", "mesh": 0, "translation": [0, 3, 0] }
+           ]"#,
+    );
+    let scene = GltfScene::from_slice(&document).unwrap();
+
+    let child = &scene.nodes[1];
+    assert_eq!(child.parent, Some(0));
+    assert_eq!(scene.nodes[0].children, [1]);
+    assert_eq!(
+        child.local_transform,
+        Mat4::from_translation(Vec3::new(0.0, 3.0, 0.0))
+    );
+    // the parent's scale applies to the child's offset too
+    assert_eq!(
+        child.world_transform.transform_point3(Vec3::ZERO),
+        Vec3::new(1.0, 6.0, 0.0)
+    );
+}
+
+#[test]
+fn nodes_that_do_not_form_a_tree_are_rejected() {
+    let uri = data_uri("application/octet-stream", &triangle_buffer());
+    let hierarchy_error = |nodes: &str| {
+        let document = triangle_document(
+            &uri,
+            &format!(
+                r#""meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}],
+                   "nodes": {}"#,
+                nodes
+            ),
+        );
+        match GltfScene::from_slice(&document) {
+            Err(GltfError::NodeHierarchy(node)) => node,
+            Err(e) => panic!("{}: {}", nodes, e),
+            Ok(_) => panic!("{} loaded", nodes),
+        }
+    };
+
+    // a node that is its own child
+    assert_eq!(
+        hierarchy_error(r#"[{ "children": [1] }, { "mesh": 0, "children": [1] }]"#),
+        1
+    );
+    // a loop further down, node 1 also ends up with two parents
+    assert_eq!(
+        hierarchy_error(
+            r#"[{ "children": [1] }, { "children": [2] }, { "mesh": 0, "children": [1] }]"#
+        ),
+        1
+    );
+    // a loop with no root above it
+    assert_eq!(
+        hierarchy_error(r#"[{ "children": [1] }, { "mesh": 0, "children": [0] }]"#),
+        0
+    );
+}
+
+#[test]
+fn an_attribute_shorter_than_the_positions_is_rejected() {
+    let error = GltfScene::from_slice(&triangle_with_normals(2, [0, 1, 2]))
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            GltfError::AttributeCount {
+                name: "NORMAL",
+                count: 2,
+                expected: 3
+            }
+        ),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn an_index_past_the_vertices_is_rejected() {
+    let error = GltfScene::from_slice(&triangle_with_normals(3, [0, 1, 5]))
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            GltfError::IndexOutOfRange {
+                index: 5,
+                vertex_count: 3
+            }
+        ),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,122 @@
+///
+/// round trips meshes through the .mesh format and checks that damaged or
+/// stale files are turned away with an error, or rebuilt, instead of being
+/// uploaded. the files go to the temp folder
+///
+use std::path::PathBuf;
+
+use rust_wgpu_game_engine::game_engine::{
+    mesh_cache::{CachedMesh, MESH_CACHE_VERSION, MeshCacheError},
+    primitives::Primitive,
+};
+
+const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+
+/// a folder of its own in the temp folder for each test
+fn test_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("mesh_cache_{}_{}", std::process::id(), name));
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+fn cube() -> CachedMesh {
+    let data = Primitive::Cuboid {
+        size: glam::Vec3::ONE,
+    }
+    .to_obj_data();
+    CachedMesh::from_obj_data(&data, 0)
+}
+
+#[test]
+fn a_mesh_survives_the_round_trip() {
+    let mesh = cube();
+
+    assert_eq!(CachedMesh::from_bytes(&mesh.to_bytes()).unwrap(), mesh);
+}
+
+#[test]
+fn an_index_past_the_vertices_is_rejected() {
+    let mut mesh = cube();
+    assert_eq!(mesh.index_size, 2);
+    mesh.index_bytes[..2].copy_from_slice(&u16::MAX.to_ne_bytes());
+
+    let error = CachedMesh::from_bytes(&mesh.to_bytes()).err().unwrap();
+    assert!(
+        matches!(
+            error,
+            MeshCacheError::IndexOutOfRange {
+                index: 65535,
+                vertex_count: 24
+            }
+        ),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn an_older_version_is_rejected() {
+    let mut bytes = cube().to_bytes();
+    bytes[4..8].copy_from_slice(&(MESH_CACHE_VERSION - 1).to_ne_bytes());
+
+    let error = CachedMesh::from_bytes(&bytes).err().unwrap();
+    assert!(
+        matches!(
+            error,
+            MeshCacheError::Version { found, expected }
+                if found == MESH_CACHE_VERSION - 1 && expected == MESH_CACHE_VERSION
+        ),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn an_older_version_on_disk_is_rebuilt() {
+    let folder = test_folder("old_version");
+    let source = folder.join("triangle.obj");
+    let cache = folder.join("triangle.mesh");
+    std::fs::write(&source, TRIANGLE).unwrap();
+    let mesh = CachedMesh::load_or_convert_at(&source.to_string_lossy(), &cache).unwrap();
+
+    let mut bytes = std::fs::read(&cache).unwrap();
+    bytes[4..8].copy_from_slice(&(MESH_CACHE_VERSION - 1).to_ne_bytes());
+    std::fs::write(&cache, bytes).unwrap();
+
+    let reloaded = CachedMesh::load_or_convert_at(&source.to_string_lossy(), &cache).unwrap();
+    assert_eq!(reloaded, mesh);
+    assert_eq!(CachedMesh::read(&cache).unwrap(), mesh);
+}
+
+#[test]
+fn a_changed_source_regenerates_the_cache() {
+    let folder = test_folder("changed_source");
+    let source = folder.join("shape.obj");
+    let cache = folder.join("shape.mesh");
+    std::fs::write(&source, TRIANGLE).unwrap();
+    let triangle = CachedMesh::load_or_convert_at(&source.to_string_lossy(), &cache).unwrap();
+    assert_eq!(triangle.index_count, 3);
+
+    std::fs::write(&source, QUAD).unwrap();
+    let quad = CachedMesh::load_or_convert_at(&source.to_string_lossy(), &cache).unwrap();
+
+    assert_eq!(quad.source_hash, CachedMesh::hash_source(QUAD.as_bytes()));
+    assert_ne!(quad.source_hash, triangle.source_hash);
+    assert_eq!(quad.lods[0].index_count, 6);
+    assert_eq!(CachedMesh::read(&cache).unwrap(), quad);
+}
+
+#[test]
+fn caches_are_not_written_next_to_the_source() {
+    let folder = test_folder("cache_path");
+    let source = folder.join("cube.obj");
+    std::fs::write(&source, TRIANGLE).unwrap();
+    let source = source.to_string_lossy();
+
+    let cache = CachedMesh::cache_path_for(&source);
+    assert!(cache.starts_with(CachedMesh::cache_dir()));
+    assert!(!cache.starts_with(&folder));
+    // the same name in another folder gets a cache of its own
+    assert_ne!(cache, CachedMesh::cache_path_for("elsewhere/cube.obj"));
+}
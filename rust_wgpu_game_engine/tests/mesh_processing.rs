@@ -0,0 +1,185 @@
+///
+/// normal and tangent generation on small hand built meshes and on the
+/// procedural shapes, checking where vertices are split and which way the
+/// generated vectors point
+///
+use glam::{Vec2, Vec3, Vec4};
+use rust_wgpu_game_engine::game_engine::{
+    mesh_processing::NormalMode, obj_parser::ObjData, primitives::Primitive,
+};
+
+fn normals(data: &ObjData) -> Vec<Vec3> {
+    data.normal.chunks_exact(3).map(Vec3::from_slice).collect()
+}
+
+fn tangents(data: &ObjData) -> Vec<Vec4> {
+    data.tangent.chunks_exact(4).map(Vec4::from_slice).collect()
+}
+
+fn assert_close(a: Vec3, b: Vec3) {
+    assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+}
+
+///
+/// two faces hinged along the x axis at a right angle, one lying flat facing
+/// +y and one standing up facing +z. vertices 0 and 1 are on the hinge
+///
+fn hinge() -> ObjData {
+    ObjData {
+        vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0],
+        texture: vec![0.0; 8],
+        indice: vec![0, 2, 1, 0, 1, 3],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn flat_normals_split_every_shared_vertex() {
+    let flat = hinge().generate_normals(NormalMode::Flat);
+
+    assert_eq!(flat.vertices.len() / 3, 6);
+    let normals = normals(&flat);
+    for &i in &flat.indice[..3] {
+        assert_close(normals[i as usize], Vec3::Y);
+    }
+    for &i in &flat.indice[3..] {
+        assert_close(normals[i as usize], Vec3::Z);
+    }
+}
+
+#[test]
+fn edges_within_the_crease_angle_are_smoothed() {
+    let smooth = hinge().generate_normals(NormalMode::Smooth {
+        crease_angle: 120.0f32.to_radians(),
+    });
+
+    assert_eq!(smooth.vertices.len() / 3, 4);
+    let normals = normals(&smooth);
+    // both faces make a right angle at the hinge, so they count the same
+    let hinge_normal = Vec3::new(0.0, 1.0, 1.0).normalize();
+    let expected = [hinge_normal, Vec3::Y, hinge_normal];
+    for (&i, expected) in smooth.indice[..3].iter().zip(expected) {
+        assert_close(normals[i as usize], expected);
+    }
+    let expected = [hinge_normal, hinge_normal, Vec3::Z];
+    for (&i, expected) in smooth.indice[3..].iter().zip(expected) {
+        assert_close(normals[i as usize], expected);
+    }
+}
+
+#[test]
+fn edges_sharper_than_the_crease_angle_stay_hard() {
+    let data = hinge();
+    let creased = data.generate_normals(NormalMode::default());
+
+    assert_eq!(creased, data.generate_normals(NormalMode::Flat));
+}
+
+#[test]
+fn mirrored_uvs_split_the_seam() {
+    // two faces side by side facing +z, u runs away from the seam at x = 0
+    // on both sides, as when one half of a texture is mirrored onto the other
+    let data = ObjData {
+        vertices: vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 0.0],
+        texture: vec![0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0],
+        normal: [0.0, 0.0, 1.0].repeat(4),
+        indice: vec![0, 2, 1, 0, 1, 3],
+        ..Default::default()
+    };
+    let result = data.generate_tangents();
+
+    assert_eq!(result.vertices.len() / 3, 6);
+    let tangents = tangents(&result);
+    for &i in &result.indice[..3] {
+        assert_eq!(tangents[i as usize], Vec4::new(1.0, 0.0, 0.0, 1.0));
+    }
+    for &i in &result.indice[3..] {
+        assert_eq!(tangents[i as usize], Vec4::new(-1.0, 0.0, 0.0, -1.0));
+    }
+}
+
+#[test]
+fn tangents_are_unit_length_and_at_right_angles_to_the_normal() {
+    let data = Primitive::Torus {
+        radius: 2.0,
+        tube_radius: 0.5,
+        segments: 24,
+        sides: 12,
+    }
+    .to_obj_data()
+    .generate_tangents();
+    let normals = normals(&data);
+    let tangents = tangents(&data);
+    assert_eq!(normals.len(), tangents.len());
+
+    for (normal, tangent) in normals.iter().zip(&tangents) {
+        assert!((tangent.truncate().length() - 1.0).abs() < 1e-4);
+        assert!(normal.dot(tangent.truncate()).abs() < 1e-4);
+        assert!(tangent.w == 1.0 || tangent.w == -1.0);
+    }
+
+    // the bitangent from w points the way v increases across every face
+    for triangle in data.indice.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| triangle[i] as usize);
+        let points = corners.map(|i| Vec3::from_slice(&data.vertices[i * 3..]));
+        let uvs = corners.map(|i| Vec2::from_slice(&data.texture[i * 2..]));
+        let (edge1, edge2) = (points[1] - points[0], points[2] - points[0]);
+        let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+        let v_direction = (edge2 * duv1.x - edge1 * duv2.x) / duv1.perp_dot(duv2);
+
+        for i in corners {
+            let bitangent = normals[i].cross(tangents[i].truncate()) * tangents[i].w;
+            assert!(bitangent.dot(v_direction) > 0.0);
+        }
+    }
+}
+
+#[test]
+fn a_mirrored_uv_quad_gets_the_mikktspace_signs() {
+    // a strip of two quads facing +z, the right one with its texture mirrored
+    // in u about the middle edge x = 1. MikkTSpace gives the left quad +x with
+    // w = 1, the right one -x with w = -1, and splits the middle edge in two
+    let data = ObjData {
+        vertices: vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 2.0, 1.0, 0.0,
+        ],
+        texture: vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 1.0],
+        normal: [0.0, 0.0, 1.0].repeat(6),
+        indice: vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4],
+        ..Default::default()
+    };
+    let result = data.generate_tangents();
+
+    assert_eq!(result.vertices.len() / 3, 8);
+    let tangents = tangents(&result);
+    for &i in &result.indice[..6] {
+        let tangent = tangents[i as usize];
+        assert_close(tangent.truncate(), Vec3::X);
+        assert_eq!(tangent.w, 1.0);
+    }
+    for &i in &result.indice[6..] {
+        let tangent = tangents[i as usize];
+        assert_close(tangent.truncate(), -Vec3::X);
+        assert_eq!(tangent.w, -1.0);
+    }
+}
+
+#[test]
+fn a_rotated_texture_turns_the_tangent() {
+    // u runs up the quad and v runs to the left, the tangent follows u
+    let data = ObjData {
+        vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+        texture: vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0],
+        normal: [0.0, 0.0, 1.0].repeat(4),
+        indice: vec![0, 1, 2, 0, 2, 3],
+        ..Default::default()
+    };
+    let result = data.generate_tangents();
+
+    assert_eq!(result.vertices.len() / 3, 4);
+    for tangent in tangents(&result) {
+        assert_close(tangent.truncate(), Vec3::Y);
+        assert_eq!(tangent.w, 1.0);
+    }
+}
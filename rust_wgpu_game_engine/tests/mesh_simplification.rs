@@ -0,0 +1,189 @@
+///
+/// QEM simplification and levels of detail on small grids: how far a mesh
+/// gets simplified, which vertices may not move, and picking a level from
+/// the size a model is drawn at
+///
+use glam::Vec3;
+use rust_wgpu_game_engine::game_engine::{
+    master_renderer::LodSelection, mesh_simplification::LodSettings, obj_parser::ObjData,
+};
+
+///
+/// n x n squares over x and z from `x` to `x + n`, with y from `height`.
+/// u runs 0 to 1 across the squares plus `u_offset`, normals are +y
+///
+fn grid(n: u32, x: f32, u_offset: f32, height: impl Fn(f32, f32) -> f32) -> ObjData {
+    let mut data = ObjData::default();
+    for row in 0..=n {
+        for column in 0..=n {
+            let (px, pz) = (x + column as f32, row as f32);
+            data.vertices.extend_from_slice(&[px, height(px, pz), pz]);
+            data.texture
+                .extend_from_slice(&[column as f32 / n as f32 + u_offset, row as f32 / n as f32]);
+            data.normal.extend_from_slice(&[0.0, 1.0, 0.0]);
+        }
+    }
+    for row in 0..n {
+        for column in 0..n {
+            let top_left = row * (n + 1) + column;
+            let bottom_left = top_left + n + 1;
+            data.indice.extend_from_slice(&[
+                top_left,
+                bottom_left,
+                top_left + 1,
+                top_left + 1,
+                bottom_left,
+                bottom_left + 1,
+            ]);
+        }
+    }
+    data
+}
+
+fn flat(n: u32) -> ObjData {
+    grid(n, 0.0, 0.0, |_, _| 0.0)
+}
+
+/// a bowl, curved along both x and z so no collapse is free
+fn bowl(n: u32) -> ObjData {
+    let middle = n as f32 / 2.0;
+    grid(n, 0.0, 0.0, move |x, z| {
+        ((x - middle).powi(2) + (z - middle).powi(2)) * 0.1
+    })
+}
+
+fn position(data: &ObjData, i: u32) -> Vec3 {
+    Vec3::from_slice(&data.vertices[i as usize * 3..])
+}
+
+fn triangle_count(indices: &[u32]) -> usize {
+    indices.len() / 3
+}
+
+#[test]
+fn a_flat_plane_reaches_the_target() {
+    let data = flat(8);
+    assert_eq!(triangle_count(&data.indice), 128);
+
+    let simplified = data.simplify(&data.indice, 64, 0.01);
+    assert!(triangle_count(&simplified) <= 64);
+    assert!(simplified.iter().all(|&i| position(&data, i).y == 0.0));
+}
+
+#[test]
+fn border_vertices_stay() {
+    let data = flat(8);
+    let simplified = data.simplify(&data.indice, 0, 1.0);
+
+    // only the outline is left, 32 border vertices need 30 triangles
+    assert_eq!(triangle_count(&simplified), 30);
+    for i in 0..data.vertices.len() as u32 / 3 {
+        let p = position(&data, i);
+        let on_border = p.x == 0.0 || p.x == 8.0 || p.z == 0.0 || p.z == 8.0;
+        assert_eq!(simplified.contains(&i), on_border, "vertex at {}", p);
+    }
+}
+
+#[test]
+fn uv_seam_vertices_stay() {
+    // two halves meeting at x = 4, their uvs do not match along it
+    let left = flat(4);
+    let right = grid(4, 4.0, 0.25, |_, _| 0.0);
+    let offset = left.vertices.len() as u32 / 3;
+    let mut data = left.clone();
+    data.vertices.extend_from_slice(&right.vertices);
+    data.texture.extend_from_slice(&right.texture);
+    data.normal.extend_from_slice(&right.normal);
+    data.indice.extend(right.indice.iter().map(|&i| i + offset));
+
+    let simplified = data.simplify(&data.indice, 0, 1.0);
+    let seam: Vec<u32> = (0..data.vertices.len() as u32 / 3)
+        .filter(|&i| position(&data, i).x == 4.0)
+        .collect();
+    assert_eq!(seam.len(), 10);
+    for i in seam {
+        assert!(simplified.contains(&i), "seam vertex {} moved", i);
+    }
+}
+
+#[test]
+fn simplification_stops_at_the_max_error() {
+    let data = bowl(8);
+
+    let untouched = data.simplify(&data.indice, 0, 1e-6);
+    assert_eq!(untouched, data.indice);
+
+    let some = data.simplify(&data.indice, 0, 0.3);
+    let more = data.simplify(&data.indice, 0, 2.0);
+    assert!(triangle_count(&some) < 128);
+    assert!(triangle_count(&more) < triangle_count(&some));
+}
+
+#[test]
+fn lods_stop_once_a_level_saves_too_little() {
+    // nothing can be collapsed within the error, so only full detail is left
+    let settings = LodSettings {
+        levels: 3,
+        reduction: 0.5,
+        max_error: 1e-7,
+    };
+    assert_eq!(bowl(8).generate_lods(&settings).len(), 1);
+
+    // a flat plane halves to 64 then 32 triangles, but the outline needs 30
+    // and 32 -> 30 is under the 10% a level has to save
+    let data = flat(8);
+    let lods = data.generate_lods(&LodSettings {
+        max_error: 0.05,
+        ..settings
+    });
+    let counts: Vec<usize> = lods.iter().map(|lod| triangle_count(lod)).collect();
+    assert_eq!(counts, [128, 64, 32]);
+}
+
+#[test]
+fn select_switches_each_halving_of_the_size() {
+    let selection = LodSelection {
+        hysteresis: 0.0,
+        ..LodSelection::default()
+    };
+
+    assert_eq!(selection.select(0.5, 0, 4), 0);
+    assert_eq!(selection.select(0.25, 0, 4), 1);
+    assert_eq!(selection.select(0.125, 0, 4), 2);
+    // never past the coarsest level there is
+    assert_eq!(selection.select(0.001, 0, 4), 3);
+    assert_eq!(selection.select(0.001, 0, 1), 0);
+}
+
+#[test]
+fn bias_moves_the_switches() {
+    let selection = LodSelection::default();
+    assert_eq!(selection.select(0.3, 0, 4), 0);
+    assert_eq!(selection.select(0.1, 0, 4), 2);
+
+    let coarser = LodSelection {
+        bias: 1.0,
+        ..selection
+    };
+    assert_eq!(coarser.select(0.3, 0, 4), 1);
+
+    let finer = LodSelection {
+        bias: -1.0,
+        ..selection
+    };
+    assert_eq!(finer.select(0.1, 0, 4), 1);
+}
+
+#[test]
+fn the_level_holds_inside_the_hysteresis_band() {
+    let selection = LodSelection::default();
+
+    // just either side of the switch to level 1, whichever was drawn stays
+    for size in [0.26, 0.23] {
+        assert_eq!(selection.select(size, 0, 4), 0, "size {}", size);
+        assert_eq!(selection.select(size, 1, 4), 1, "size {}", size);
+    }
+    // further out the level changes
+    assert_eq!(selection.select(0.2, 0, 4), 1);
+    assert_eq!(selection.select(0.3, 1, 4), 0);
+}
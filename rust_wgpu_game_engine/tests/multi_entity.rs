@@ -0,0 +1,285 @@
+///
+/// renders several entities offscreen and checks each is drawn where it
+/// stands and with its own mesh, not all on top of the last one written or
+/// with the mesh of another model that shares its texture. uploads that only
+/// fail against a device are checked here too.
+/// needs a gpu or a software adapter such as lavapipe, and fails without one
+///
+use glam::Vec3;
+use rust_wgpu_game_engine::game_engine::{
+    asset_manager::AssetManager,
+    camera::Camera,
+    depth_texture::DepthSettings,
+    entity::Entity,
+    light::Light,
+    loader::Loader,
+    main_shader::MainShader,
+    master_renderer::MasterRenderer,
+    primitives::Primitive,
+    renderer::Renderer,
+    texture_data::{TextureError, TextureOptions},
+    textured_model::TexturedModel,
+};
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 144;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const CLEAR: [u8; 4] = [0, 0, 0, 255];
+
+fn create_device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: false,
+        ..Default::default()
+    }))
+    .expect("no wgpu adapter, these tests need a gpu or a software adapter");
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+        .expect("the adapter did not give a device")
+}
+
+/// same layout as the one the loader builds its texture bind groups with
+fn texture_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
+
+/// draws the entities `create` makes and reads the frame back as rgba rows
+fn render(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    create: impl FnOnce(Loader) -> Vec<Entity>,
+) -> Vec<u8> {
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: FORMAT,
+        width: WIDTH,
+        height: HEIGHT,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    };
+    let depth = DepthSettings::default();
+    let uniform_layout = Renderer::create_uniform_layout(device);
+    let shader = MainShader::new(
+        device,
+        &config,
+        &texture_layout(device),
+        &uniform_layout,
+        depth,
+    );
+    let mut master_renderer = MasterRenderer::new(device, &config, &uniform_layout, depth);
+
+    for entity in create(Loader::new(device, queue.clone())) {
+        master_renderer.add_entity(entity);
+    }
+    let mut camera = Camera::new();
+    camera.position.z = 10.0;
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Target"),
+        size: wgpu::Extent3d {
+            width: WIDTH,
+            height: HEIGHT,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+    // a row of 256 pixels is already a multiple of COPY_BYTES_PER_ROW_ALIGNMENT
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: (WIDTH * HEIGHT * 4) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(master_renderer.depth_stencil_attachment()),
+            ..Default::default()
+        });
+        master_renderer.render(&mut render_pass, queue, &shader, &Light::new(), &camera);
+    }
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture: &target,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(WIDTH * 4),
+                rows_per_image: Some(HEIGHT),
+            },
+        },
+        wgpu::Extent3d {
+            width: WIDTH,
+            height: HEIGHT,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("readback finishes");
+    readback.slice(..).get_mapped_range().to_vec()
+}
+
+fn is_covered(pixels: &[u8], x: u32, y: u32) -> bool {
+    let i = ((y * WIDTH + x) * 4) as usize;
+    pixels[i..i + 4] != CLEAR
+}
+
+/// the runs of columns with anything drawn in them, as (first, last) pairs
+fn covered_column_runs(pixels: &[u8]) -> Vec<(u32, u32)> {
+    let covered = |x: u32| (0..HEIGHT).any(|y| is_covered(pixels, x, y));
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for x in (0..WIDTH).filter(|&x| covered(x)) {
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == x => *last = x,
+            _ => runs.push((x, x)),
+        }
+    }
+    runs
+}
+
+#[test]
+fn entities_sharing_a_model_are_drawn_in_their_own_places() {
+    let (device, queue) = create_device();
+
+    let positions = [
+        Vec3::new(-4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+    ];
+    let pixels = render(&device, &queue, |mut loader| {
+        let model = TexturedModel::new(
+            &loader.load_primitive(Primitive::Cuboid {
+                size: Vec3::splat(1.0),
+            }),
+            &loader.load_texture(),
+        );
+        positions
+            .iter()
+            .map(|&position| Entity::new(model.clone(), position, 0.0, 0.0, 0.0, 1.0, 0))
+            .collect()
+    });
+    let runs = covered_column_runs(&pixels);
+    assert_eq!(runs.len(), positions.len(), "drawn column runs: {:?}", runs);
+
+    // left to right like the entities, each roughly centred where its x projects
+    let centres: Vec<f32> = runs
+        .iter()
+        .map(|&(first, last)| (first + last) as f32 / 2.0 / WIDTH as f32)
+        .collect();
+    assert!(centres[0] < 0.4 && (0.4..0.6).contains(&centres[1]) && centres[2] > 0.6);
+}
+
+#[test]
+fn models_sharing_a_texture_handle_keep_their_own_meshes() {
+    let (device, queue) = create_device();
+
+    let pixels = render(&device, &queue, |loader| {
+        let mut assets = AssetManager::new(loader);
+        let options = TextureOptions::default();
+        let texture = assets
+            .load_texture("res/terrain/mud.png", &options)
+            .unwrap();
+        let shared = assets
+            .load_texture("res/terrain/mud.png", &options)
+            .unwrap();
+        assert!(texture.ptr_eq(&shared));
+
+        let tall = assets.get_loader().load_primitive(Primitive::Cuboid {
+            size: Vec3::new(1.0, 3.0, 1.0),
+        });
+        let flat = assets.get_loader().load_primitive(Primitive::Cuboid {
+            size: Vec3::new(1.0, 0.5, 1.0),
+        });
+        vec![
+            Entity::new(
+                TexturedModel::new(&tall, &texture.get().unwrap()),
+                Vec3::new(-4.0, 0.0, 0.0),
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+                0,
+            ),
+            Entity::new(
+                TexturedModel::new(&flat, &shared.get().unwrap()),
+                Vec3::new(4.0, 0.0, 0.0),
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+                0,
+            ),
+        ]
+    });
+
+    let runs = covered_column_runs(&pixels);
+    assert_eq!(runs.len(), 2, "drawn column runs: {:?}", runs);
+    // how many rows each is drawn over, the tall one should be about six times the flat one
+    let heights: Vec<usize> = runs
+        .iter()
+        .map(|&(first, last)| {
+            (0..HEIGHT)
+                .filter(|&y| (first..=last).any(|x| is_covered(&pixels, x, y)))
+                .count()
+        })
+        .collect();
+    assert!(heights[0] > 3 * heights[1], "drawn heights: {:?}", heights);
+}
+
+#[test]
+fn an_empty_mip_chain_is_an_error() {
+    let (device, queue) = create_device();
+    let loader = Loader::new(&device, queue);
+
+    let error = loader
+        .create_texture_view(&[], &TextureOptions::default())
+        .unwrap_err();
+    assert!(matches!(error, TextureError::NoLevels), "{}", error);
+}
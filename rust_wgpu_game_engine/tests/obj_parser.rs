@@ -0,0 +1,407 @@
+///
+/// reads small hand written obj and mtl files through the parsers, covering the parts
+/// of the format exporters disagree on. the files go to the temp folder
+///
+use std::path::PathBuf;
+
+use glam::Vec3;
+use rust_wgpu_game_engine::game_engine::{
+    material::Material,
+    mesh_cache::CachedMesh,
+    obj_error::{ObjError, ObjErrorKind},
+    obj_parser,
+    raw_model::RawModel,
+};
+
+/// writes `contents` to a file of its own in the temp folder
+fn write_file(name: &str, contents: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("obj_parser_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let path = folder.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// loads a file that should not load
+fn load_error(name: &str, contents: &str) -> ObjError {
+    let path = write_file(name, contents);
+    match obj_parser::load_obj_file(&path.to_string_lossy()) {
+        Ok(_) => panic!("{} loaded", name),
+        Err(e) => e,
+    }
+}
+
+/// three corners of a triangle
+const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+#[test]
+fn a_missing_file_is_not_tied_to_a_line() {
+    let error = obj_parser::load_obj_file("res/not_there.obj").unwrap_err();
+
+    assert!(
+        matches!(error.kind, ObjErrorKind::MissingFile(_)),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 0);
+    assert_eq!(error.path, "res/not_there.obj");
+    assert!(error.to_string().starts_with("res/not_there.obj: "));
+}
+
+#[test]
+fn a_bad_float_names_its_line_and_value() {
+    let error = load_error("bad_float.obj", "# a comment\nv 0 0 0\nv 1 x 0\n");
+
+    assert!(
+        matches!(&error.kind, ObjErrorKind::MalformedFloat(value) if value == "x"),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 3);
+
+    // a coordinate that is not there at all
+    let error = load_error("short_vertex.obj", "v 0 0\n");
+    assert!(
+        matches!(&error.kind, ObjErrorKind::MalformedFloat(value) if value.is_empty()),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn a_bad_index_names_its_line_and_value() {
+    let error = load_error("bad_index.obj", &format!("{}f 1 two 3\n", TRIANGLE));
+
+    assert!(
+        matches!(&error.kind, ObjErrorKind::MalformedIndex(value) if value == "two"),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 4);
+}
+
+#[test]
+fn indices_past_what_was_read_are_out_of_range() {
+    let out_of_range = |name: &str, face: &str| {
+        let error = load_error(name, &format!("{}{}\n", TRIANGLE, face));
+        assert_eq!(error.line, 4, "{}", error);
+        match error.kind {
+            ObjErrorKind::IndexOutOfRange { index, len } => (index, len),
+            _ => panic!("{}", error),
+        }
+    };
+
+    assert_eq!(out_of_range("past_the_end.obj", "f 1 2 4"), (4, 3));
+    // obj indices start at 1
+    assert_eq!(out_of_range("zero.obj", "f 0 1 2"), (0, 3));
+    assert_eq!(out_of_range("too_negative.obj", "f -4 -3 -2"), (-4, 3));
+    assert_eq!(out_of_range("no_uvs.obj", "f 1/1 2/1 3/1"), (1, 0));
+    assert_eq!(out_of_range("no_normals.obj", "f 1//1 2//1 3//1"), (1, 0));
+}
+
+#[test]
+fn a_face_needs_three_corners() {
+    let error = load_error("line.obj", &format!("{}f 1 2\n", TRIANGLE));
+
+    assert!(
+        matches!(error.kind, ObjErrorKind::FaceTooSmall(2)),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 4);
+    assert!(
+        error
+            .to_string()
+            .ends_with(":4: face has 2 corners, need at least 3")
+    );
+}
+
+#[test]
+fn corners_must_all_have_a_texture_coordinate_or_none() {
+    let error = load_error(
+        "mixed_uvs.obj",
+        &format!("{}vt 0 0\nf 1/1 2/1 3\n", TRIANGLE),
+    );
+
+    assert!(
+        matches!(error.kind, ObjErrorKind::MissingTextureCoordinate),
+        "{}",
+        error
+    );
+    assert_eq!(error.line, 5);
+}
+
+/// positions of the corners of every triangle
+fn triangles(data: &obj_parser::ObjData) -> Vec<[Vec3; 3]> {
+    let position = |i: u32| Vec3::from_slice(&data.vertices[i as usize * 3..]);
+    data.indice
+        .chunks(3)
+        .map(|t| [position(t[0]), position(t[1]), position(t[2])])
+        .collect()
+}
+
+/// twice the area, pointing along the triangle's normal
+fn doubled_area(t: &[Vec3; 3]) -> Vec3 {
+    (t[1] - t[0]).cross(t[2] - t[0])
+}
+
+#[test]
+fn quads_and_larger_faces_are_triangulated() {
+    let path = write_file(
+        "quad_and_pentagon.obj",
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         v 0 0 1\nv 2 0 1\nv 3 1 1\nv 1 2 1\nv -1 1 1\n\
+         f 1 2 3 4\nf 5 6 7 8 9\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    // n corners make n - 2 triangles
+    assert_eq!(data.indice.len(), (2 + 3) * 3);
+    assert_eq!(data.vertices.len() / 3, 9);
+    for triangle in triangles(&data) {
+        // every triangle keeps the counter clockwise winding of its face
+        assert!(doubled_area(&triangle).z > 0.0, "{:?}", triangle);
+    }
+    let area: f32 = triangles(&data)[..2]
+        .iter()
+        .map(|t| doubled_area(t).length() / 2.0)
+        .sum();
+    assert!((area - 1.0).abs() < 1e-5, "{}", area);
+}
+
+#[test]
+fn concave_faces_are_ear_clipped() {
+    // an arrow head, the corner at (2, 1) points in. a fan from the first
+    // corner would put a flipped triangle across the notch
+    let points = [
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(4.0, 4.0, 0.0),
+        Vec3::new(2.0, 1.0, 0.0),
+        Vec3::new(0.0, 4.0, 0.0),
+    ];
+    let corners = obj_parser::triangulate(&points);
+    assert_eq!(corners.len(), 3);
+
+    let mut area = 0.0;
+    for [a, b, c] in corners {
+        let doubled = doubled_area(&[points[a], points[b], points[c]]);
+        assert!(doubled.z > 0.0, "{:?} is flipped", [a, b, c]);
+        area += doubled.z / 2.0;
+    }
+    // the triangles cover the arrow exactly once
+    assert!((area - 10.0).abs() < 1e-5, "{}", area);
+
+    // the same face read from a file
+    let path = write_file(
+        "arrow.obj",
+        "v 0 0 0\nv 4 0 0\nv 4 4 0\nv 2 1 0\nv 0 4 0\nvn 0 0 1\n\
+         f 1//1 2//1 3//1 4//1 5//1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+    let area: f32 = triangles(&data)
+        .iter()
+        .map(|t| doubled_area(t).z / 2.0)
+        .sum();
+    assert!((area - 10.0).abs() < 1e-5, "{}", area);
+}
+
+#[test]
+fn negative_indices_count_back_from_what_was_read() {
+    let path = write_file(
+        "negative.obj",
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5 0.5\nvn 0 0 1\n\
+         f -3/-1/-1 -2/-1/-1 -1/-1/-1\n\
+         v 5 5 5\nv 6 5 5\nv 5 6 5\n\
+         f -3/1/1 -2/1/1 -1/1/1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    let triangles = triangles(&data);
+    assert_eq!(
+        triangles[0],
+        [Vec3::ZERO, Vec3::X, Vec3::Y],
+        "the first face only sees the first three vertices"
+    );
+    assert_eq!(
+        triangles[1],
+        [
+            Vec3::new(5.0, 5.0, 5.0),
+            Vec3::new(6.0, 5.0, 5.0),
+            Vec3::new(5.0, 6.0, 5.0)
+        ]
+    );
+    assert_eq!(&data.texture[..2], [0.5, 0.5]);
+}
+
+#[test]
+fn every_face_form_is_read() {
+    let path = write_file(
+        "face_forms.obj",
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.25 0.75\nvn 0 0 1\n\
+         f 1 2 3\nf 1/1 2/1 3/1\nf 1//1 2//1 3//1\nf 1/1/1 2/1/1 3/1/1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    assert_eq!(data.indice.len(), 4 * 3);
+    for (corner, &index) in data.indice.iter().enumerate() {
+        let index = index as usize;
+        let uv = &data.texture[index * 2..index * 2 + 2];
+        let normal = Vec3::from_slice(&data.normal[index * 3..]);
+        // faces without vt get (0, 0), every face here faces +z
+        let expected_uv = if (3..6).contains(&corner) || corner >= 9 {
+            [0.25, 0.75]
+        } else {
+            [0.0, 0.0]
+        };
+        assert_eq!(uv, expected_uv, "corner {}", corner);
+        assert!(normal.abs_diff_eq(Vec3::Z, 1e-5), "corner {}", corner);
+    }
+}
+
+/// an object file of `vertex_count` vertices, a triangle for every three
+fn many_vertices(vertex_count: usize) -> String {
+    let mut text = String::with_capacity(vertex_count * 24);
+    for i in 0..vertex_count {
+        let x = (i / 3) as f32;
+        text.push_str(&format!(
+            "v {} {} {}\n",
+            x,
+            (i % 3 == 1) as u8,
+            (i % 3 == 2) as u8
+        ));
+    }
+    for first in (1..=vertex_count - 2).step_by(3) {
+        text.push_str(&format!("f {} {} {}\n", first, first + 1, first + 2));
+    }
+    // the last vertex or two still belong to a face
+    if !vertex_count.is_multiple_of(3) {
+        let last = vertex_count;
+        text.push_str(&format!("f {} {} {}\n", last - 2, last - 1, last));
+    }
+    text
+}
+
+#[test]
+fn corners_sharing_an_index_triple_share_a_vertex() {
+    // a cube - 8 positions, 6 normals, 12 triangles
+    let path = write_file(
+        "cube.obj",
+        "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+         v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+         vn 0 0 -1\nvn 0 0 1\nvn 0 -1 0\nvn 0 1 0\nvn -1 0 0\nvn 1 0 0\n\
+         f 1//1 4//1 3//1 2//1\nf 5//2 6//2 7//2 8//2\n\
+         f 1//3 2//3 6//3 5//3\nf 4//4 8//4 7//4 3//4\n\
+         f 1//5 5//5 8//5 4//5\nf 2//6 3//6 7//6 6//6\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    // each corner of a face has its own normal, so 4 vertices a face
+    assert_eq!(data.vertices.len() / 3, 24);
+    assert_eq!(data.normal.len() / 3, 24);
+    assert_eq!(data.texture.len() / 2, 24);
+    assert_eq!(data.indice.len(), 36);
+
+    // two triangles of a flat square share the corners on their diagonal
+    let path = write_file(
+        "square.obj",
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\n\
+         f 1//1 2//1 3//1\nf 1//1 3//1 4//1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+    assert_eq!(data.vertices.len() / 3, 4);
+    assert_eq!(data.indice, [0, 1, 2, 0, 2, 3]);
+}
+
+#[test]
+fn a_different_texture_coordinate_splits_a_vertex() {
+    let path = write_file(
+        "seam.obj",
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0.5\n\
+         f 1/1 2/2 3/3\nf 1/1 3/3 4/4\nf 1/5 3/3 4/4\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    // the last face uses position 1 with another uv, so it gets a fifth vertex
+    assert_eq!(data.vertices.len() / 3, 5);
+    assert_eq!(data.indice, [0, 1, 2, 0, 2, 3, 4, 2, 3]);
+    assert_eq!(&data.texture[8..10], [0.5, 0.5]);
+}
+
+#[test]
+fn indices_widen_to_32_bits_past_65535_vertices() {
+    for (vertex_count, format, index_size) in [
+        (65_535, wgpu::IndexFormat::Uint16, 2),
+        (65_536, wgpu::IndexFormat::Uint32, 4),
+    ] {
+        let path = write_file(
+            &format!("vertices_{}.obj", vertex_count),
+            &many_vertices(vertex_count),
+        );
+        let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+        let count = data.vertices.len() / 3;
+        assert_eq!(count, vertex_count);
+        assert_eq!(
+            *data.indice.iter().max().unwrap() as usize,
+            vertex_count - 1
+        );
+        assert_eq!(RawModel::index_format_for(count), format);
+        assert_eq!(CachedMesh::index_size_for(count), index_size);
+
+        let mesh = CachedMesh::from_obj_data(&data, 0);
+        assert_eq!(mesh.index_size, index_size);
+        assert_eq!(
+            mesh.index_bytes.len(),
+            mesh.index_count as usize * index_size as usize
+        );
+    }
+}
+
+#[test]
+fn tab_separated_lines_are_read() {
+    let path = write_file(
+        "tabs.obj",
+        "v\t0 0 0\nv\t1 0 0\nv 0\t1\t0\nvt\t0.5 0.25\nvn 0 0\t1\nf\t1/1/1 2/1/1\t3/1/1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    assert_eq!(data.vertices.len() / 3, 3);
+    assert_eq!(data.indice, [0, 1, 2]);
+    assert_eq!(&data.texture[..2], [0.5, 0.25]);
+    assert_eq!(&data.normal[..3], [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn texture_coordinates_without_v_get_zero() {
+    let path = write_file(
+        "one_component_uv.obj",
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.75\nf 1/1 2/1 3/1\n",
+    );
+    let data = obj_parser::load_obj_file(&path.to_string_lossy()).unwrap();
+
+    assert_eq!(&data.texture[..2], [0.75, 0.0]);
+}
+
+#[test]
+fn a_missing_material_library_leaves_faces_without_a_material() {
+    let path = write_file(
+        "missing_library.obj",
+        "mtllib not_there.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+    );
+    let meshes = obj_parser::load_obj_meshes(&path.to_string_lossy()).unwrap();
+
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].material_name.as_deref(), Some("red"));
+    assert!(meshes[0].material.is_none());
+}
+
+#[test]
+fn a_single_value_colour_is_a_grey() {
+    let path = write_file("grey.mtl", "newmtl grey\nKd 0.5\nKs 0.2 0.3 0.4\n");
+    let materials = Material::load_mtl_file(&path.to_string_lossy()).unwrap();
+
+    assert_eq!(materials["grey"].diffuse_colour, Vec3::splat(0.5));
+    assert_eq!(materials["grey"].specular_colour, Vec3::new(0.2, 0.3, 0.4));
+}
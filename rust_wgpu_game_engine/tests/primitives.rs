@@ -0,0 +1,221 @@
+///
+/// the procedural meshes on the cpu - normals, winding, uvs and how many
+/// triangles each shape is built from
+///
+use glam::{Vec2, Vec3};
+use rust_wgpu_game_engine::game_engine::{obj_parser::ObjData, primitives::Primitive};
+
+fn position(data: &ObjData, i: u32) -> Vec3 {
+    Vec3::from_slice(&data.vertices[i as usize * 3..])
+}
+
+fn normal(data: &ObjData, i: u32) -> Vec3 {
+    Vec3::from_slice(&data.normal[i as usize * 3..])
+}
+
+fn tex_coord(data: &ObjData, i: u32) -> Vec2 {
+    Vec2::from_slice(&data.texture[i as usize * 2..])
+}
+
+/// one of every shape, with the number of triangles it should have
+fn shapes() -> Vec<(Primitive, usize)> {
+    vec![
+        (
+            Primitive::Plane {
+                width: 4.0,
+                depth: 2.0,
+                subdivisions: 3,
+            },
+            3 * 3 * 2,
+        ),
+        (
+            // the rings touching the poles have one triangle per segment
+            Primitive::UvSphere {
+                radius: 1.5,
+                segments: 12,
+                rings: 8,
+            },
+            12 * (8 * 2 - 2),
+        ),
+        (
+            Primitive::Icosphere {
+                radius: 2.0,
+                subdivisions: 2,
+            },
+            20 * 4 * 4,
+        ),
+        (
+            // the side and both caps
+            Primitive::Cylinder {
+                radius: 1.0,
+                height: 3.0,
+                segments: 10,
+            },
+            10 * 2 + 10 * 2,
+        ),
+        (
+            Primitive::Cone {
+                radius: 1.0,
+                height: 2.0,
+                segments: 10,
+            },
+            10 + 10,
+        ),
+        (
+            Primitive::Torus {
+                radius: 2.0,
+                tube_radius: 0.5,
+                segments: 16,
+                sides: 8,
+            },
+            16 * 8 * 2,
+        ),
+        (
+            // two half spheres of 4 rings and the straight part between them
+            Primitive::Capsule {
+                radius: 0.5,
+                height: 2.0,
+                segments: 12,
+                rings: 4,
+            },
+            12 * (4 * 2 - 1) * 2 + 12 * 2,
+        ),
+        (
+            Primitive::Cuboid {
+                size: Vec3::new(1.0, 2.0, 3.0),
+            },
+            6 * 2,
+        ),
+    ]
+}
+
+/// where a normal on the surface at `position` points away from
+fn inside_point(primitive: &Primitive, position: Vec3) -> Vec3 {
+    match *primitive {
+        // the middle of the tube nearest to the point
+        Primitive::Torus { radius, .. } => {
+            Vec3::new(position.x, 0.0, position.z).normalize_or_zero() * radius
+        }
+        // below the plane
+        Primitive::Plane { .. } => Vec3::new(position.x, -1.0, position.z),
+        _ => Vec3::ZERO,
+    }
+}
+
+#[test]
+fn shapes_have_the_expected_number_of_triangles() {
+    for (primitive, triangles) in shapes() {
+        let data = primitive.to_obj_data();
+
+        assert_eq!(data.indice.len(), triangles * 3, "{:?}", primitive);
+        let vertex_count = (data.vertices.len() / 3) as u32;
+        assert!(
+            data.indice.iter().all(|&i| i < vertex_count),
+            "{:?}",
+            primitive
+        );
+        assert_eq!(data.normal.len(), data.vertices.len(), "{:?}", primitive);
+        assert_eq!(data.texture.len() / 2, data.vertices.len() / 3);
+    }
+
+    // 4 corners a face, so every face can have its own normal and uvs
+    let cuboid = Primitive::Cuboid { size: Vec3::ONE }.to_obj_data();
+    assert_eq!(cuboid.vertices.len() / 3, 24);
+}
+
+#[test]
+fn normals_are_unit_length_and_point_outwards() {
+    for (primitive, _) in shapes() {
+        let data = primitive.to_obj_data();
+        for i in 0..(data.vertices.len() / 3) as u32 {
+            let (position, normal) = (position(&data, i), normal(&data, i));
+            assert!(
+                (normal.length() - 1.0).abs() < 1e-4,
+                "{:?} vertex {} normal {}",
+                primitive,
+                i,
+                normal
+            );
+            let outwards = position - inside_point(&primitive, position);
+            assert!(
+                normal.dot(outwards) > 0.0,
+                "{:?} vertex {} at {} has normal {}",
+                primitive,
+                i,
+                position,
+                normal
+            );
+        }
+    }
+}
+
+#[test]
+fn triangles_wind_counter_clockwise_seen_from_their_normals() {
+    for (primitive, _) in shapes() {
+        let data = primitive.to_obj_data();
+        for triangle in data.indice.chunks(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| position(&data, i));
+            let face = (b - a).cross(c - a);
+            assert!(
+                face.length() > 1e-6,
+                "{:?} has a zero area triangle {:?}",
+                primitive,
+                triangle
+            );
+            let normals: Vec3 = triangle.iter().map(|&i| normal(&data, i)).sum();
+            assert!(
+                face.dot(normals) > 0.0,
+                "{:?} triangle {:?} faces away from its normals",
+                primitive,
+                triangle
+            );
+        }
+    }
+}
+
+#[test]
+fn uvs_are_inside_the_texture() {
+    for (primitive, _) in shapes() {
+        let data = primitive.to_obj_data();
+        // icosphere triangles across the seam carry on past u = 1, for a
+        // repeating sampler
+        let max_u = match primitive {
+            Primitive::Icosphere { .. } => 1.5,
+            _ => 1.0,
+        };
+        for i in 0..(data.vertices.len() / 3) as u32 {
+            let uv = tex_coord(&data, i);
+            assert!(
+                (0.0..=max_u).contains(&uv.x) && (0.0..=1.0).contains(&uv.y),
+                "{:?} vertex {} uv {}",
+                primitive,
+                i,
+                uv
+            );
+        }
+    }
+}
+
+#[test]
+fn shapes_are_the_size_asked_for() {
+    let plane = Primitive::Plane {
+        width: 4.0,
+        depth: 2.0,
+        subdivisions: 1,
+    }
+    .to_obj_data();
+    let corners: Vec<Vec3> = (0..4).map(|i| position(&plane, i)).collect();
+    assert_eq!(corners[0], Vec3::new(-2.0, 0.0, -1.0));
+    assert_eq!(corners[3], Vec3::new(2.0, 0.0, 1.0));
+
+    for radius in [0.5, 3.0] {
+        let sphere = Primitive::Icosphere {
+            radius,
+            subdivisions: 1,
+        }
+        .to_obj_data();
+        for i in 0..(sphere.vertices.len() / 3) as u32 {
+            assert!((position(&sphere, i).length() - radius).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+///
+/// where projections put points in depth, and the depth buffer setup each
+/// one asks for
+///
+use glam::{Vec3, Vec4};
+use rust_wgpu_game_engine::game_engine::projection::Projection;
+
+const NEAR: f32 = 0.1;
+const FAR: f32 = 100.0;
+
+fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+/// depth of a point `distance` in front of the camera, which looks down -z
+fn depth(projection: &Projection, distance: f32) -> f32 {
+    let clip = projection.matrix() * Vec4::new(0.0, 0.0, -distance, 1.0);
+    clip.z / clip.w
+}
+
+/// depth of a point infinitely far away, straight ahead
+fn depth_at_infinity(projection: &Projection) -> f32 {
+    let clip = projection.matrix() * Vec4::new(0.0, 0.0, -1.0, 0.0);
+    clip.z / clip.w
+}
+
+/// whether a fragment at depth `a` is drawn over one already at `b`
+fn drawn_over(projection: &Projection, a: f32, b: f32) -> bool {
+    match projection.depth_settings().compare {
+        wgpu::CompareFunction::Less => a < b,
+        wgpu::CompareFunction::LessEqual => a <= b,
+        wgpu::CompareFunction::Greater => a > b,
+        wgpu::CompareFunction::GreaterEqual => a >= b,
+        other => panic!("{:?} is not a depth test", other),
+    }
+}
+
+fn reversed(mut projection: Projection) -> Projection {
+    projection.reverse_z = true;
+    projection
+}
+
+/// every projection, with and without reverse_z
+fn projections() -> Vec<Projection> {
+    let forward = [
+        Projection::perspective(1.0, 1.5, NEAR, Some(FAR)),
+        Projection::perspective(1.0, 1.5, NEAR, None),
+        Projection::orthographic(10.0, 1.5, NEAR, FAR),
+    ];
+    forward.iter().flat_map(|&p| [p, reversed(p)]).collect()
+}
+
+#[test]
+fn perspective_maps_near_to_0_and_far_to_1() {
+    let projection = Projection::perspective(1.0, 1.5, NEAR, Some(FAR));
+
+    assert_near(depth(&projection, NEAR), 0.0);
+    assert_near(depth(&projection, FAR), 1.0);
+    assert!(depth(&projection, 10.0) > depth(&projection, 1.0));
+}
+
+#[test]
+fn reverse_z_maps_near_to_1_and_far_to_0() {
+    let projection = reversed(Projection::perspective(1.0, 1.5, NEAR, Some(FAR)));
+
+    assert_near(depth(&projection, NEAR), 1.0);
+    assert_near(depth(&projection, FAR), 0.0);
+    assert!(depth(&projection, 10.0) < depth(&projection, 1.0));
+
+    // most of the float range is left for the distance, not the first few units
+    assert!(depth(&projection, 1.0) < 0.2, "{}", depth(&projection, 1.0));
+}
+
+#[test]
+fn an_infinite_far_plane_never_reaches_the_far_depth() {
+    let projection = Projection::perspective(1.0, 1.5, NEAR, None);
+    assert_near(depth(&projection, NEAR), 0.0);
+    let distant = depth(&projection, 1.0e6);
+    assert!(distant < 1.0 && distant > 0.9999, "{}", distant);
+    assert_near(depth_at_infinity(&projection), 1.0);
+
+    let projection = reversed(projection);
+    assert_near(depth(&projection, NEAR), 1.0);
+    let distant = depth(&projection, 1.0e6);
+    assert!(distant > 0.0 && distant < 1.0e-4, "{}", distant);
+    assert_near(depth_at_infinity(&projection), 0.0);
+}
+
+#[test]
+fn orthographic_depth_is_linear() {
+    let projection = Projection::orthographic(10.0, 1.5, NEAR, FAR);
+    assert_near(depth(&projection, NEAR), 0.0);
+    assert_near(depth(&projection, FAR), 1.0);
+    assert_near(depth(&projection, (NEAR + FAR) / 2.0), 0.5);
+
+    let projection = reversed(projection);
+    assert_near(depth(&projection, NEAR), 1.0);
+    assert_near(depth(&projection, FAR), 0.0);
+    assert_near(depth(&projection, (NEAR + FAR) / 2.0), 0.5);
+
+    // `height` units top to bottom, `height * aspect` across, at any distance
+    for distance in [1.0, 50.0] {
+        let corner = projection.matrix() * Vec3::new(7.5, 5.0, -distance).extend(1.0);
+        assert_near(corner.x / corner.w, 1.0);
+        assert_near(corner.y / corner.w, 1.0);
+    }
+}
+
+#[test]
+fn depth_settings_match_the_depth_range() {
+    for projection in projections() {
+        let settings = projection.depth_settings();
+        let (compare, clear) = if projection.reverse_z {
+            (wgpu::CompareFunction::Greater, 0.0)
+        } else {
+            (wgpu::CompareFunction::Less, 1.0)
+        };
+        assert_eq!(settings.compare, compare, "{:?}", projection);
+        assert_eq!(settings.clear_value(), clear, "{:?}", projection);
+    }
+}
+
+#[test]
+fn nearer_points_win_the_depth_test() {
+    for projection in projections() {
+        let clear = projection.depth_settings().clear_value();
+        let (near, middle, far) = (
+            depth(&projection, 1.0),
+            depth(&projection, 10.0),
+            depth(&projection, 90.0),
+        );
+
+        assert!(drawn_over(&projection, near, middle), "{:?}", projection);
+        assert!(drawn_over(&projection, middle, far), "{:?}", projection);
+        assert!(!drawn_over(&projection, far, near), "{:?}", projection);
+        // anything in range is drawn over a cleared buffer
+        assert!(drawn_over(&projection, far, clear), "{:?}", projection);
+    }
+}
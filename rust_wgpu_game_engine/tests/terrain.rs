@@ -0,0 +1,111 @@
+///
+/// the cpu side of terrains - the mesh built from a grid of heights and the
+/// height lookup entities are put on the ground with
+///
+use glam::Vec3;
+use rust_wgpu_game_engine::game_engine::{
+    obj_parser::ObjData,
+    terrain::{SIZE, Terrain},
+};
+
+fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+}
+
+fn position(data: &ObjData, i: u32) -> Vec3 {
+    Vec3::from_slice(&data.vertices[i as usize * 3..])
+}
+
+/// one square, heights top left, top right, bottom left, bottom right
+const SQUARE: [f32; 4] = [0.0, 4.0, 8.0, 20.0];
+
+/// a slope rising `rise` for every unit along +x
+fn slope(vertex_count: usize, rise: f32) -> Vec<f32> {
+    let spacing = SIZE / (vertex_count - 1) as f32;
+    (0..vertex_count * vertex_count)
+        .map(|i| (i % vertex_count) as f32 * spacing * rise)
+        .collect()
+}
+
+#[test]
+fn the_mesh_has_a_vertex_per_height_and_two_triangles_per_square() {
+    let data = Terrain::generate_mesh(&[0.0; 16], 4);
+
+    assert_eq!(data.vertices.len() / 3, 16);
+    assert_eq!(data.indice.len(), 3 * 3 * 6);
+    assert!(data.indice.iter().all(|&i| i < 16));
+    assert_eq!(&data.texture[..2], [0.0, 0.0]);
+    assert_eq!(&data.texture[30..], [1.0, 1.0]);
+    assert_eq!(position(&data, 15), Vec3::new(SIZE, 0.0, SIZE));
+}
+
+#[test]
+fn triangles_face_up() {
+    for heights in [vec![0.0; 9], slope(3, 0.5)] {
+        let data = Terrain::generate_mesh(&heights, 3);
+        for triangle in data.indice.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| position(&data, triangle[i]));
+            assert!((b - a).cross(c - a).y > 0.0);
+        }
+    }
+}
+
+#[test]
+fn normals_lean_away_from_the_slope() {
+    let data = Terrain::generate_mesh(&slope(4, 0.5), 4);
+    let expected = Vec3::new(-0.5, 1.0, 0.0).normalize();
+
+    for normal in data.normal.chunks_exact(3).map(Vec3::from_slice) {
+        assert!(normal.abs_diff_eq(expected, 1e-5), "{}", normal);
+    }
+    let flat = Terrain::generate_mesh(&[3.0; 9], 3);
+    for normal in flat.normal.chunks_exact(3).map(Vec3::from_slice) {
+        assert_eq!(normal, Vec3::Y);
+    }
+}
+
+#[test]
+fn heights_at_the_vertices_are_exact() {
+    let heights: Vec<f32> = (0..16).map(|i| ((i * 7) % 5) as f32 - 2.0).collect();
+    let spacing = SIZE / 3.0;
+
+    for row in 0..4 {
+        for column in 0..4 {
+            let height =
+                Terrain::height_in_mesh(&heights, 4, column as f32 * spacing, row as f32 * spacing);
+            assert_near(height, heights[row * 4 + column]);
+        }
+    }
+}
+
+#[test]
+fn heights_are_interpolated_across_both_triangles() {
+    // the top left triangle is the plane 4x + 8z, the bottom right -8 + 12x + 16z
+    let at = |x: f32, z: f32| Terrain::height_in_mesh(&SQUARE, 2, x * SIZE, z * SIZE);
+
+    assert_near(at(0.25, 0.25), 3.0);
+    assert_near(at(0.75, 0.75), 13.0);
+    // both triangles agree along the diagonal they share
+    assert_near(at(0.5, 0.5), 6.0);
+    assert_near(at(0.25, 0.75), 7.0);
+    assert_near(at(0.75, 0.25), 5.0);
+}
+
+#[test]
+fn the_far_edge_belongs_to_the_last_square() {
+    assert_near(Terrain::height_in_mesh(&SQUARE, 2, SIZE, SIZE), 20.0);
+    assert_near(Terrain::height_in_mesh(&SQUARE, 2, SIZE, SIZE / 2.0), 12.0);
+    assert_near(Terrain::height_in_mesh(&SQUARE, 2, SIZE / 2.0, SIZE), 14.0);
+}
+
+#[test]
+fn outside_the_terrain_is_zero() {
+    for (x, z) in [
+        (-1.0, 10.0),
+        (SIZE + 1.0, 10.0),
+        (10.0, -0.1),
+        (10.0, SIZE + 0.1),
+    ] {
+        assert_eq!(Terrain::height_in_mesh(&[5.0; 4], 2, x, z), 0.0);
+    }
+}
@@ -0,0 +1,267 @@
+///
+/// packing images into atlases, and the manifest that says where they went.
+/// the atlas_packer tool is run on a folder of pngs in the temp folder
+///
+use std::{path::PathBuf, process::Command};
+
+use glam::Vec2;
+use image::{Rgba, RgbaImage};
+use rust_wgpu_game_engine::game_engine::texture_atlas::{
+    AtlasError, AtlasManifest, AtlasRegion, DEFAULT_MAX_ATLAS_SIZE, TextureAtlas,
+};
+
+/// an image of `width` x `height` filled with a colour unique to `seed`
+fn image(name: &str, width: u32, height: u32, seed: u8) -> (String, RgbaImage) {
+    (
+        name.to_string(),
+        RgbaImage::from_pixel(width, height, Rgba([seed, 255 - seed, seed / 2, 255])),
+    )
+}
+
+/// the pixel rect a region covers, left, top, right, bottom
+fn pixels(region: &AtlasRegion, atlas: &RgbaImage) -> (u32, u32, u32, u32) {
+    let size = Vec2::new(atlas.width() as f32, atlas.height() as f32);
+    let min = (region.offset * size).round();
+    let max = ((region.offset + region.size) * size).round();
+    (min.x as u32, min.y as u32, max.x as u32, max.y as u32)
+}
+
+fn temp_folder(name: &str) -> PathBuf {
+    let folder =
+        std::env::temp_dir().join(format!("texture_atlas_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+#[test]
+fn rects_are_in_bounds_and_do_not_overlap() {
+    let images: Vec<_> = [
+        (30, 10),
+        (8, 40),
+        (64, 16),
+        (5, 5),
+        (17, 33),
+        (40, 40),
+        (1, 60),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, &(w, h))| image(&format!("image {}", i), w, h, i as u8 * 30))
+    .collect();
+    let padding = 2;
+    let atlas = TextureAtlas::pack_rects(&images, padding, DEFAULT_MAX_ATLAS_SIZE).unwrap();
+    let AtlasManifest::Rects { entries } = &atlas.manifest else {
+        panic!("not a rect manifest");
+    };
+    assert_eq!(entries.len(), images.len());
+
+    let rects: Vec<_> = entries
+        .iter()
+        .map(|(_, region)| pixels(region, &atlas.image))
+        .collect();
+    for (((name, source), (entry, _)), &(left, top, right, bottom)) in
+        images.iter().zip(entries).zip(&rects)
+    {
+        assert_eq!(name, entry);
+        assert_eq!((right - left, bottom - top), source.dimensions());
+        assert!(
+            left >= padding && top >= padding,
+            "{} touches the edge",
+            name
+        );
+        assert!(
+            right + padding <= atlas.image.width() && bottom + padding <= atlas.image.height(),
+            "{} is outside the atlas",
+            name
+        );
+        assert_eq!(atlas.image.get_pixel(left, top), source.get_pixel(0, 0));
+        assert_eq!(
+            atlas.image.get_pixel(right - 1, bottom - 1),
+            source.get_pixel(0, 0)
+        );
+    }
+
+    // with the padding around both, no two rects may touch
+    for (i, a) in rects.iter().enumerate() {
+        for b in &rects[i + 1..] {
+            let apart = a.2 + padding * 2 <= b.0
+                || b.2 + padding * 2 <= a.0
+                || a.3 + padding * 2 <= b.1
+                || b.3 + padding * 2 <= a.1;
+            assert!(apart, "{:?} and {:?} overlap", a, b);
+        }
+    }
+}
+
+#[test]
+fn grid_cells_hold_their_images() {
+    let images: Vec<_> = (0..5)
+        .map(|i| image(&format!("cell {}", i), 4, 4, i as u8 * 50))
+        .collect();
+    let atlas = TextureAtlas::pack_grid(&images).unwrap();
+
+    assert_eq!(atlas.image.dimensions(), (12, 12));
+    for (i, (name, source)) in images.iter().enumerate() {
+        assert_eq!(atlas.manifest.get_texture_index(name), Some(i as u32));
+        let (left, top, right, bottom) =
+            pixels(&atlas.manifest.get_region(name).unwrap(), &atlas.image);
+        assert_eq!((right - left, bottom - top), (4, 4));
+        assert_eq!(atlas.image.get_pixel(left, top), source.get_pixel(0, 0));
+    }
+}
+
+#[test]
+fn grid_images_must_be_the_same_size() {
+    let images = [image("a", 4, 4, 0), image("b", 4, 5, 1)];
+    let error = TextureAtlas::pack_grid(&images).err().unwrap();
+
+    assert!(
+        matches!(&error, AtlasError::SizeMismatch { name, found: (4, 5), .. } if name == "b"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn a_rect_that_does_not_fit_is_an_error() {
+    // wider than the atlas may be, once padded
+    let images = [image("small", 4, 4, 0), image("wide", 63, 4, 1)];
+    let error = TextureAtlas::pack_rects(&images, 1, 64).err().unwrap();
+    assert!(
+        matches!(&error, AtlasError::DoesNotFit { name, max_size: 64 } if name == "wide"),
+        "{}",
+        error
+    );
+
+    // every image fits on its own but the shelves run out of height
+    let images: Vec<_> = (0..5)
+        .map(|i| image(&format!("tall {}", i), 40, 20, i))
+        .collect();
+    let error = TextureAtlas::pack_rects(&images, 0, 64).err().unwrap();
+    assert!(
+        matches!(error, AtlasError::DoesNotFit { max_size: 64, .. }),
+        "{}",
+        error
+    );
+
+    assert!(TextureAtlas::pack_rects(&images[..3], 0, 64).is_ok());
+}
+
+#[test]
+fn nothing_to_pack_is_an_error() {
+    assert!(matches!(
+        TextureAtlas::pack_rects(&[], 0, DEFAULT_MAX_ATLAS_SIZE),
+        Err(AtlasError::Empty)
+    ));
+    assert!(matches!(
+        TextureAtlas::pack_grid(&[]),
+        Err(AtlasError::Empty)
+    ));
+}
+
+#[test]
+fn manifests_survive_being_written_out() {
+    let grid = AtlasManifest::Grid {
+        number_of_rows: 3,
+        entries: vec![("grass".to_string(), 0), ("dry dirt".to_string(), 7)],
+    };
+    assert_eq!(AtlasManifest::parse(&grid.to_text()).unwrap(), grid);
+
+    let rects = AtlasManifest::Rects {
+        entries: vec![
+            (
+                "stone wall".to_string(),
+                AtlasRegion {
+                    offset: Vec2::new(0.0, 0.5),
+                    size: Vec2::new(0.25, 0.125),
+                },
+            ),
+            (
+                "thirds".to_string(),
+                AtlasRegion {
+                    offset: Vec2::new(1.0 / 3.0, 2.0 / 3.0),
+                    size: Vec2::new(0.1, 0.7),
+                },
+            ),
+        ],
+    };
+    assert_eq!(AtlasManifest::parse(&rects.to_text()).unwrap(), rects);
+}
+
+#[test]
+fn malformed_manifest_lines_are_errors() {
+    let line_of = |text: &str| match AtlasManifest::parse(text) {
+        Err(AtlasError::Manifest(line, _)) => line,
+        other => panic!("{:?} parsed as {:?}", text, other),
+    };
+
+    assert_eq!(line_of("atlas 3\n"), 1);
+    assert_eq!(line_of("grid three\n"), 1);
+    assert_eq!(line_of("grid\n"), 1);
+    assert_eq!(line_of("grid 2\ngrass 0\n\ndirt\n"), 4);
+    assert_eq!(line_of("grid 2\ngrass zero\n"), 2);
+    // a grid needs a row, and its indices have to be inside it
+    assert_eq!(line_of("grid 0\n"), 1);
+    assert_eq!(line_of("grid 0\ngrass 0\n"), 1);
+    assert_eq!(line_of("grid 2\ngrass 3\ndirt 4\n"), 3);
+    assert_eq!(line_of("rect\nstone 0 0 0.5\n"), 2);
+    assert_eq!(line_of("rect\nstone 0 0 0.5 0.5\nwall 0 x 0.5 0.5\n"), 3);
+    assert!(matches!(
+        AtlasManifest::parse("\n\n"),
+        Err(AtlasError::Empty)
+    ));
+}
+
+#[test]
+fn the_packer_writes_an_atlas_and_its_manifest() {
+    let folder = temp_folder("packer");
+    for (i, (w, h)) in [(16, 8), (8, 8), (4, 12)].into_iter().enumerate() {
+        let (_, source) = image("", w, h, i as u8 * 60);
+        source.save(folder.join(format!("tile {}.png", i))).unwrap();
+    }
+    std::fs::write(folder.join("notes.txt"), "not an image").unwrap();
+    let output = folder.join("out").join("atlas.png");
+    std::fs::create_dir_all(output.parent().unwrap()).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_atlas_packer"))
+        .arg(&folder)
+        .arg(&output)
+        .args(["--rects", "--padding", "1"])
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+
+    let atlas = image::open(&output).unwrap().to_rgba8();
+    let manifest =
+        AtlasManifest::from_path(&output.with_extension("atlas").to_string_lossy()).unwrap();
+    for (i, size) in [(16, 8), (8, 8), (4, 12)].into_iter().enumerate() {
+        let (left, top, right, bottom) = pixels(
+            &manifest.get_region(&format!("tile {}", i)).unwrap(),
+            &atlas,
+        );
+        assert_eq!((right - left, bottom - top), size);
+    }
+
+    // too small to hold the widest tile
+    let status = Command::new(env!("CARGO_BIN_EXE_atlas_packer"))
+        .arg(&folder)
+        .arg(&output)
+        .args(["--rects", "--max-size", "8"])
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1));
+
+    // grids need tiles of one size
+    let status = Command::new(env!("CARGO_BIN_EXE_atlas_packer"))
+        .arg(&folder)
+        .arg(&output)
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1));
+
+    let _ = std::fs::remove_dir_all(&folder);
+}
@@ -0,0 +1,173 @@
+///
+/// decodes images and builds mip chains and sampler descriptors without a
+/// device. TextureError::TooLarge is only raised against a device's limits
+/// when uploading, so it is not covered here
+///
+use std::io::Cursor;
+
+use glam::Vec4;
+use rust_wgpu_game_engine::game_engine::texture_data::{
+    SamplerDesc, TextureData, TextureError, TextureOptions,
+};
+
+fn encode(image: image::DynamicImage) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+/// an image of `width` x `height` filled with one rgba colour
+fn filled(width: u32, height: u32, colour: [u8; 4]) -> TextureData {
+    TextureData {
+        width,
+        height,
+        pixels: colour.repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn a_missing_file_names_its_path() {
+    let error = TextureData::from_path("res/not_there.png").unwrap_err();
+
+    assert!(matches!(error, TextureError::Io(..)), "{}", error);
+    assert!(
+        error.to_string().starts_with("res/not_there.png: "),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn bytes_that_are_not_an_image_are_rejected() {
+    let error = TextureData::from_bytes(b"not an image").unwrap_err();
+
+    assert!(matches!(error, TextureError::Image(_)), "{}", error);
+}
+
+#[test]
+fn greyscale_images_are_widened_to_rgba() {
+    let grey = image::GrayImage::from_pixel(3, 2, image::Luma([100]));
+    let data = TextureData::from_bytes(&encode(image::DynamicImage::ImageLuma8(grey))).unwrap();
+
+    assert_eq!((data.width, data.height), (3, 2));
+    assert_eq!(data.pixels, [100, 100, 100, 255].repeat(6));
+}
+
+#[test]
+fn the_mip_chain_goes_down_to_one_texel() {
+    assert_eq!(filled(256, 64, [0; 4]).mip_level_count(), 9);
+    assert_eq!(filled(5, 3, [0; 4]).mip_level_count(), 3);
+    assert_eq!(filled(1, 1, [0; 4]).mip_level_count(), 1);
+
+    let sizes: Vec<_> = filled(5, 3, [0; 4])
+        .generate_mipmaps(true)
+        .iter()
+        .map(|level| (level.width, level.height, level.pixels.len()))
+        .collect();
+    assert_eq!(sizes, [(5, 3, 60), (2, 1, 8), (1, 1, 4)]);
+}
+
+#[test]
+fn srgb_levels_are_averaged_in_linear_space() {
+    let black_and_white = TextureData {
+        width: 2,
+        height: 1,
+        pixels: vec![0, 0, 0, 0, 255, 255, 255, 255],
+    };
+
+    // half the light of white is much brighter than half the srgb value
+    let srgb = black_and_white.downsample(true);
+    assert_eq!(srgb.pixels, [188, 188, 188, 128]);
+
+    let linear = black_and_white.downsample(false);
+    assert_eq!(linear.pixels, [128, 128, 128, 128]);
+}
+
+#[test]
+fn odd_sizes_keep_their_last_row_and_column() {
+    // 3x3 of black with a white last column and a white last row
+    let mut data = filled(3, 3, [0, 0, 0, 255]);
+    for i in 0..3 {
+        for texel in [i * 3 + 2, 6 + i] {
+            data.pixels[texel * 4..texel * 4 + 3].copy_from_slice(&[255; 3]);
+        }
+    }
+
+    // 5 of the 9 texels are white
+    let half = data.downsample(false);
+    assert_eq!((half.width, half.height), (1, 1));
+    assert_eq!(half.pixels, [142, 142, 142, 255]);
+
+    // the left over column is shared out to the last texel of each row
+    let row = TextureData {
+        width: 5,
+        height: 1,
+        pixels: [[0; 4], [0; 4], [0; 4], [0; 4], [255; 4]].concat(),
+    };
+    let half = row.downsample(false);
+    assert_eq!((half.width, half.height), (2, 1));
+    assert_eq!(half.pixels, [0, 0, 0, 0, 85, 85, 85, 85]);
+}
+
+#[test]
+fn atlases_are_not_mipmapped() {
+    let options = TextureOptions::atlas();
+
+    assert!(!options.generate_mipmaps);
+    assert!(options.srgb);
+}
+
+#[test]
+fn solid_colours_are_clamped() {
+    let data = TextureData::solid_colour(Vec4::new(2.0, -1.0, 0.5, 1.0));
+
+    assert_eq!((data.width, data.height), (1, 1));
+    assert_eq!(data.pixels, [255, 0, 128, 255]);
+}
+
+#[test]
+fn clamp_to_border_needs_the_device_feature() {
+    let sampler = SamplerDesc {
+        address_mode_u: wgpu::AddressMode::ClampToBorder,
+        border_colour: Some(wgpu::SamplerBorderColor::TransparentBlack),
+        ..SamplerDesc::repeat()
+    };
+
+    let without = sampler.to_descriptor(wgpu::Features::empty());
+    assert_eq!(without.address_mode_u, wgpu::AddressMode::ClampToEdge);
+    assert_eq!(without.address_mode_v, wgpu::AddressMode::Repeat);
+
+    let with = sampler.to_descriptor(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER);
+    assert_eq!(with.address_mode_u, wgpu::AddressMode::ClampToBorder);
+    assert_eq!(
+        with.border_color,
+        Some(wgpu::SamplerBorderColor::TransparentBlack)
+    );
+}
+
+#[test]
+fn anisotropy_only_applies_to_linear_filtering() {
+    let linear = SamplerDesc {
+        anisotropy: 64,
+        ..SamplerDesc::default()
+    };
+    assert_eq!(
+        linear
+            .to_descriptor(wgpu::Features::empty())
+            .anisotropy_clamp,
+        16
+    );
+
+    let nearest = SamplerDesc {
+        anisotropy: 8,
+        ..SamplerDesc::pixel_art()
+    };
+    assert_eq!(
+        nearest
+            .to_descriptor(wgpu::Features::empty())
+            .anisotropy_clamp,
+        1
+    );
+}
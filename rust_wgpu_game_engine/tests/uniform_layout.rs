@@ -0,0 +1,108 @@
+///
+/// checks the hand padded uniform structs against the layout WGSL gives the
+/// same structs in shader.wgsl, as encase works it out. encase only knows an
+/// older glam, so the vectors go through a local vec3 of its own
+///
+use glam::Vec3;
+use rust_wgpu_game_engine::game_engine::light::{Light, LightingUniforms};
+use wgsl::{WgslLight, WgslLighting};
+
+/// the structs of shader.wgsl as encase sees them
+// the derive leaves a size check function per field that nothing calls
+#[allow(dead_code)]
+mod wgsl {
+    use super::{Light, Vec3};
+
+    #[derive(Copy, Clone)]
+    pub struct WgslVec3([f32; 3]);
+
+    impl AsRef<[f32; 3]> for WgslVec3 {
+        fn as_ref(&self) -> &[f32; 3] {
+            &self.0
+        }
+    }
+
+    impl AsMut<[f32; 3]> for WgslVec3 {
+        fn as_mut(&mut self) -> &mut [f32; 3] {
+            &mut self.0
+        }
+    }
+
+    impl From<[f32; 3]> for WgslVec3 {
+        fn from(value: [f32; 3]) -> Self {
+            WgslVec3(value)
+        }
+    }
+
+    encase::impl_vector!(3, WgslVec3, f32; using AsRef AsMut From);
+
+    impl From<Vec3> for WgslVec3 {
+        fn from(value: Vec3) -> Self {
+            WgslVec3(value.to_array())
+        }
+    }
+
+    /// `Light` in shader.wgsl
+    #[derive(encase::ShaderType)]
+    pub struct WgslLight {
+        pub position: WgslVec3,
+        pub colour: WgslVec3,
+    }
+
+    /// `Lighting` in shader.wgsl
+    #[derive(encase::ShaderType)]
+    pub struct WgslLighting {
+        pub sun: WgslLight,
+        pub camera_position: WgslVec3,
+        pub shine_damper: f32,
+        pub reflectivity: f32,
+        pub ambient: f32,
+    }
+
+    impl From<&Light> for WgslLight {
+        fn from(light: &Light) -> Self {
+            WgslLight {
+                position: light.position.into(),
+                colour: light.colour.into(),
+            }
+        }
+    }
+}
+
+/// every field different and non-zero, so a field in the wrong place shows
+fn sun() -> Light {
+    let mut sun = Light::new();
+    sun.position = Vec3::new(1.0, 2.0, 3.0);
+    sun.colour = Vec3::new(4.0, 5.0, 6.0);
+    sun
+}
+
+#[test]
+fn light_matches_its_wgsl_layout() {
+    let sun = sun();
+    let mut buffer = encase::UniformBuffer::new(Vec::<u8>::new());
+    buffer.write(&WgslLight::from(&sun)).unwrap();
+
+    assert_eq!(buffer.into_inner(), bytemuck::bytes_of(&sun));
+}
+
+#[test]
+fn lighting_uniforms_match_their_wgsl_layout() {
+    let sun = sun();
+    let mut lighting = LightingUniforms::new(&sun, Vec3::new(7.0, 8.0, 9.0), 0.2);
+    lighting.shine_damper = 10.0;
+    lighting.reflectivity = 0.5;
+
+    let mut buffer = encase::UniformBuffer::new(Vec::<u8>::new());
+    buffer
+        .write(&WgslLighting {
+            sun: WgslLight::from(&sun),
+            camera_position: lighting.camera_position.into(),
+            shine_damper: lighting.shine_damper,
+            reflectivity: lighting.reflectivity,
+            ambient: lighting.ambient,
+        })
+        .unwrap();
+
+    assert_eq!(buffer.into_inner(), bytemuck::bytes_of(&lighting));
+}